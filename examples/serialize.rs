@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use bevy::prelude::*;
 use bevy_inspector_egui::WorldInspectorPlugin;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 enum InputAction {
     Jump,
     Load,
@@ -14,7 +14,7 @@ enum InputAction {
     Reset,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 enum InputAxis {
     Horizontal,
     Vertical,
@@ -23,11 +23,17 @@ enum InputAxis {
 #[derive(Component)]
 struct InputStateText;
 
-static BINDINGS_PATH: &str = "assets\\bindings\\example.bindings";
+// shipped with the game, relative to the asset folder
+static DEFAULT_BINDINGS: &str = "bindings/example.bindings";
+// the player's changes, relative to the user config directory
+static USER_BINDINGS: &str = "example.bindings";
 
 type Input = ActionInput<InputAction, InputAxis>;
 type Map = ActionMap<InputAction, InputAxis>;
 
+// the startup load of the player's bindings, the defaults are loaded when it fails
+struct UserBindingsLoad(MapIoRequestId);
+
 #[derive(Debug)]
 struct Ui {
     binding_list_entity: Entity,
@@ -38,7 +44,8 @@ struct Ui {
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
-        .add_plugin(ActionInputPlugin::<InputAction, InputAxis>::default())
+        .add_action_input_systems_with_axis::<InputAction, InputAxis>()
+        .add_action_serialize_systems_with_axis::<InputAction, InputAxis>()
         .add_plugin(WorldInspectorPlugin::new())
         .add_startup_system(setup)
         .add_system(handle_input)
@@ -136,27 +143,35 @@ fn setup(
 
     commands.insert_resource(ui);
 
-    input_map_io_w.send(MapIoRequest::Load(BINDINGS_PATH.into()));
+    let player_e = commands.spawn().insert(Map::default()).id();
+    let request = MapIoRequest::load(player_e, BindingsLocation::user_config(USER_BINDINGS));
+    commands.insert_resource(UserBindingsLoad(request.id()));
+    input_map_io_w.send(request);
 }
 
 fn handle_input(
-    input: Res<Input>,
+    input_q: Query<(Entity, &Input)>,
     mut map_ev_w: EventWriter<MapIoRequest>
 ) {
-    if input.just_released(InputAction::Save) {
-        map_ev_w.send(MapIoRequest::Save(BINDINGS_PATH.into()));
-    }
-    else if input.just_released(InputAction::Load) {
-        map_ev_w.send(MapIoRequest::Load(BINDINGS_PATH.into()));
+    for (e, input) in input_q.iter() {
+        if input.just_released(InputAction::Save) {
+            map_ev_w.send(MapIoRequest::save(e, BindingsLocation::user_config(USER_BINDINGS)));
+        }
+        else if input.just_released(InputAction::Load) {
+            map_ev_w.send(MapIoRequest::load(e, BindingsLocation::user_config(USER_BINDINGS)));
+        }
     }
 }
 
 fn reset_map(
-    mut map: ResMut<Map>,
+    mut map_q: Query<(Entity, &mut Map, &Input)>,
     mut map_ev_w: EventWriter<MapIoRequest>,
-    input: Res<Input>,
 ) -> Result<(), BindingError> {
-    if input.just_released(InputAction::Reset) {
+    for (e, mut map, input) in map_q.iter_mut() {
+        if !input.just_released(InputAction::Reset) {
+            continue;
+        }
+
         map.clear_bindings();
         map
             .bind_button_action(InputAction::Jump, KeyCode::Space)?
@@ -169,7 +184,7 @@ fn reset_map(
             .bind_axis(InputAxis::Horizontal, AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX))
             .bind_axis(InputAxis::Vertical, AxisBinding::GamepadAxis(GamepadAxisType::LeftStickY));
     
-        map_ev_w.send(MapIoRequest::Save(BINDINGS_PATH.into()))
+        map_ev_w.send(MapIoRequest::save(e, BindingsLocation::user_config(USER_BINDINGS)));
     }
 
     Ok(())
@@ -177,13 +192,33 @@ fn reset_map(
 
 fn update_bindings_ui(
     mut ev: EventReader<MapIoEvent>,
+    mut map_ev_w: EventWriter<MapIoRequest>,
     mut commands: Commands,
     ui: Res<Ui>,
+    user_load: Res<UserBindingsLoad>,
     children_q: Query<&Children>,
-    map: Res<Map>,
+    map_q: Query<&Map>,
 ) {
     for ev in ev.iter() {
-        if let MapIoEvent::Loaded = ev {
+        let map = match ev {
+            MapIoEvent::Loaded { entity, .. } => map_q.get(*entity),
+            MapIoEvent::LoadFailed { id, entity, .. } if *id == user_load.0 => {
+                // nothing saved yet
+                map_ev_w.send(MapIoRequest::load(*entity, BindingsLocation::assets(DEFAULT_BINDINGS)));
+                continue;
+            }
+            MapIoEvent::LoadFailed { path, error, .. } => {
+                warn!("Failed to load bindings from {}: {}", path, error);
+                continue;
+            }
+            MapIoEvent::SaveFailed { path, error, .. } => {
+                warn!("Failed to save bindings to {}: {}", path, error);
+                continue;
+            }
+            MapIoEvent::Saved { .. } => continue,
+        };
+
+        if let Ok(map) = map {
             if let Ok(children) = children_q.get(ui.binding_list_entity) {
                 for e in children.iter() {
                     commands.entity(*e).despawn_recursive();
@@ -248,7 +283,13 @@ fn update_bindings_ui(
     }
 }
 
-fn actions_state_ui(input: Res<ActionInput<InputAction, InputAxis>>, mut query: Query<&mut Text, With<InputStateText>>) {
+fn actions_state_ui(input_q: Query<&Input>, mut query: Query<&mut Text, With<InputStateText>>) {
+    let input = if let Some(input) = input_q.iter().next() {
+        input
+    } else {
+        return;
+    };
+
     for mut text in query.iter_mut() {
         text.sections[0].value = format!(
             "{:?}\n{:?}\n\n{:?}\n{:?}\n\n{:?}\n{:?}\n\n{:?}\n{:?}\n\n{:?}\n{:?}",
@@ -0,0 +1,191 @@
+use crate::{input_time::InputTime, ActionInput, ActionMapInput, ActionState, InputTimestamp};
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionTransition {
+    Pressed,
+    Released,
+}
+
+// sent for every press and release of a button action, in the order the transitions happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionEvent<TKeyAction> {
+    pub entity: Entity,
+    pub action: TKeyAction,
+    pub player_id: Option<usize>,
+    pub transition: ActionTransition,
+    pub timestamp: InputTimestamp,
+    // how long the action was held, 0 for presses
+    pub duration: f32,
+}
+
+pub(crate) fn emit_action_events<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    input_q: Query<(Entity, &ActionInput<TKeyAction, TAxisAction>)>,
+    mut event_w: EventWriter<ActionEvent<TKeyAction>>,
+    time: Res<InputTime>,
+) {
+    let mut events = Vec::new();
+    for (entity, input) in input_q.iter() {
+        for (action_data, state) in &input.button_actions {
+            let (transition, timestamp) = match state {
+                ActionState::Pressed => (
+                    ActionTransition::Pressed,
+                    input.press_timestamps.get(action_data).copied(),
+                ),
+                ActionState::Released(data) => (ActionTransition::Released, data.released_at),
+                ActionState::Held(..) | ActionState::Used => continue,
+            };
+
+            events.push(ActionEvent {
+                entity,
+                action: action_data.value,
+                player_id: action_data.id,
+                transition,
+                timestamp: timestamp.unwrap_or_else(|| time.now()),
+                duration: state.duration(),
+            });
+        }
+    }
+
+    events.sort_by(|a, b| {
+        a.timestamp
+            .partial_cmp(&b.timestamp)
+            .unwrap_or(Ordering::Equal)
+    });
+    event_w.send_batch(events.into_iter());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActionEvent, ActionTransition};
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction},
+        ButtonStateSettings, InputSimulationExt, InputTimestamp,
+    };
+    use bevy::{
+        ecs::event::{Events, ManualEventReader},
+        prelude::*,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn setup_app(event_driven: bool) -> (App, Entity) {
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Left.into()),
+                (TestAction::Shoot, MouseButton::Right.into()),
+            ],
+            [],
+        );
+
+        let mut app = test_app();
+        app.insert_resource(ButtonStateSettings { event_driven });
+        let e = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.5);
+        (app, e)
+    }
+
+    fn events(
+        app: &App,
+        reader: &mut ManualEventReader<ActionEvent<TestAction>>,
+    ) -> Vec<(TestAction, ActionTransition, InputTimestamp)> {
+        reader
+            .iter(app.world.resource::<Events<ActionEvent<TestAction>>>())
+            .map(|ev| (ev.action, ev.transition, ev.timestamp))
+            .collect()
+    }
+
+    #[test]
+    fn events_are_ordered_within_a_frame() {
+        let (mut app, e) = setup_app(true);
+        let mut reader = ManualEventReader::default();
+
+        app.simulate_press(MouseButton::Right)
+            .simulate_press(KeyCode::Left)
+            .simulate_release(MouseButton::Right);
+        app.advance_frames(1, 0.5);
+
+        // keyboard events are read before mouse events
+        assert_eq!(
+            vec![
+                (
+                    TestAction::Jump,
+                    ActionTransition::Pressed,
+                    InputTimestamp::new(1., 0)
+                ),
+                (
+                    TestAction::Shoot,
+                    ActionTransition::Pressed,
+                    InputTimestamp::new(1., 1)
+                ),
+            ],
+            events(&app, &mut reader)
+        );
+
+        // the release is reported a frame later, but keeps the time it happened at
+        app.simulate_release(KeyCode::Left);
+        app.advance_frames(1, 0.5);
+        assert_eq!(
+            vec![
+                (
+                    TestAction::Shoot,
+                    ActionTransition::Released,
+                    InputTimestamp::new(1., 2)
+                ),
+                (
+                    TestAction::Jump,
+                    ActionTransition::Released,
+                    InputTimestamp::new(1.5, 0)
+                ),
+            ],
+            events(&app, &mut reader)
+        );
+
+        let input = input(&app, e);
+        assert_eq!(
+            Some(InputTimestamp::new(1., 0)),
+            input.pressed_at(TestAction::Jump)
+        );
+        assert_eq!(
+            Some(InputTimestamp::new(1.5, 0)),
+            input.released_at(TestAction::Jump)
+        );
+    }
+
+    #[test]
+    fn polled_transitions_use_the_frame_time() {
+        let (mut app, e) = setup_app(false);
+        let mut reader = ManualEventReader::default();
+
+        app.simulate_press(KeyCode::Left);
+        app.advance_frames(2, 0.5);
+        let input = input(&app, e);
+        assert_eq!(
+            Some(InputTimestamp::new(1., 0)),
+            input.pressed_at(TestAction::Jump)
+        );
+        assert_eq!(None, input.released_at(TestAction::Jump));
+        assert_eq!(
+            vec![(
+                TestAction::Jump,
+                ActionTransition::Pressed,
+                InputTimestamp::new(1., 0)
+            )],
+            events(&app, &mut reader)
+        );
+
+        app.simulate_release(KeyCode::Left);
+        app.advance_frames(1, 0.5);
+        assert_eq!(
+            vec![(
+                TestAction::Jump,
+                ActionTransition::Released,
+                InputTimestamp::new(2., 0)
+            )],
+            events(&app, &mut reader)
+        );
+    }
+}
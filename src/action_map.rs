@@ -1,18 +1,37 @@
-use crate::{app_ext::NoAxis, validation::BindingError};
+use crate::{
+    app_ext::NoAxis,
+    button_events::ButtonStateSettings,
+    device::{ActiveDeviceSettings, InputDevice},
+    injection::InjectedInput,
+    input_time::{InputTime, InputTimestamp},
+    muting::{is_action_muted, is_axis_muted, InputMuted},
+    pointer::Pointer,
+    touch::{TouchGesture, TouchRegion, TouchStick},
+    validation::BindingError,
+};
 use bevy::{
-    input::gamepad::{GamepadAxisType, GamepadEvent, GamepadEventType},
+    input::{
+        gamepad::{GamepadAxisType, GamepadEvent, GamepadEventType},
+        keyboard::KeyboardInput,
+        ElementState,
+    },
     prelude::*,
     reflect::{TypeUuid, Uuid},
 };
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     fmt::Debug,
     hash::Hash,
 };
 
-const DEADZONE_PRECISION: f32 = 10000.;
+pub(crate) const DEADZONE_PRECISION: f32 = 10000.;
 
 pub trait ActionMapInput = Debug + Hash + Eq + Clone + Copy + Send + Sync;
+// actions of maps that are saved, loaded, or recorded, saved files list them in `Ord` order
+#[cfg(feature = "serialize")]
+pub trait SerializableActionInput =
+    ActionMapInput + Ord + serde::Serialize + serde::de::DeserializeOwned;
 
 pub(crate) type KeyActionBinding = HashSet<ButtonCode>;
 
@@ -25,6 +44,8 @@ pub(crate) type AxisBindings<TAxisAction> =
 pub enum AxisBinding {
     Buttons(ButtonCode, ButtonCode),
     GamepadAxis(GamepadAxisType),
+    TouchStickX(TouchStick),
+    TouchStickY(TouchStick),
 }
 
 // todo: impl with into
@@ -33,8 +54,12 @@ pub enum AxisBinding {
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonCode {
     Kb(KeyCode),
+    // physical key position - stays the same regardless of the keyboard layout
+    ScanCode(u32),
     Gamepad(GamepadButtonType),
     Mouse(MouseButton),
+    Touch(TouchRegion),
+    Gesture(TouchGesture),
 }
 
 impl ButtonCode {
@@ -50,7 +75,15 @@ impl ButtonCode {
         Self::Mouse(mouse_button)
     }
 
-    fn player_data(self, id: Option<usize>) -> PlayerData<Self> {
+    fn touch_region(region: TouchRegion) -> Self {
+        Self::Touch(region)
+    }
+
+    fn touch_gesture(gesture: TouchGesture) -> Self {
+        Self::Gesture(gesture)
+    }
+
+    pub(crate) fn player_data(self, id: Option<usize>) -> PlayerData<Self> {
         PlayerData::<Self> { value: self, id }
     }
 }
@@ -74,14 +107,27 @@ impl From<MouseButton> for ButtonCode {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl From<TouchRegion> for ButtonCode {
+    fn from(region: TouchRegion) -> Self {
+        Self::touch_region(region)
+    }
+}
+
+impl From<TouchGesture> for ButtonCode {
+    fn from(gesture: TouchGesture) -> Self {
+        Self::touch_gesture(gesture)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ButtonState {
     Pressed,
     Held,
     Released,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActionState {
     Pressed,
     Held(ActiveKeyData),
@@ -96,6 +142,21 @@ impl ActionState {
             ActionState::Held(data) | ActionState::Released(data) => data.duration,
         }
     }
+
+    // `None` on the frame of the press, `ActionInput::pressed_at` covers that frame as well
+    pub fn pressed_at(&self) -> Option<InputTimestamp> {
+        match self {
+            ActionState::Held(data) | ActionState::Released(data) => Some(data.pressed_at),
+            ActionState::Pressed | ActionState::Used => None,
+        }
+    }
+
+    pub fn released_at(&self) -> Option<InputTimestamp> {
+        match self {
+            ActionState::Released(data) => data.released_at,
+            _ => None,
+        }
+    }
 }
 
 impl Debug for ActionState {
@@ -109,9 +170,14 @@ impl Debug for ActionState {
     }
 }
 
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActiveKeyData {
     pub duration: f32,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub pressed_at: InputTimestamp,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub released_at: Option<InputTimestamp>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -151,23 +217,81 @@ impl<T> From<T> for PlayerData<T> {
 
 #[derive(Component, Debug, Clone)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serialize",
+    serde(bound = "TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput")
+)]
 pub struct ActionMap<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput = NoAxis> {
+    #[cfg_attr(feature = "serialize", serde(with = "map_entries"))]
     pub(crate) key_action_bindings: KeyBindings<TKeyAction>,
+    #[cfg_attr(feature = "serialize", serde(with = "map_entries"))]
     pub(crate) axis_action_bindings: AxisBindings<TAxisAction>,
     #[cfg_attr(feature = "serialize", serde(skip))]
-    bound_keys: HashSet<PlayerData<ButtonCode>>,
+    pub(crate) bound_keys: HashSet<PlayerData<ButtonCode>>,
     #[cfg_attr(feature = "serialize", serde(skip))]
     bound_axes: HashSet<GamepadAxisType>,
     #[cfg_attr(feature = "serialize", serde(skip))]
+    pub(crate) bound_touch_sticks: HashSet<TouchStick>,
+    #[cfg_attr(feature = "serialize", serde(skip))]
     pub(crate) bound_key_combinations:
         Vec<(PlayerData<HashSet<ButtonCode>>, Vec<HashSet<ButtonCode>>)>,
 }
 
+// maps are stored as lists of entries as not every format supports non-string keys
+#[cfg(feature = "serialize")]
+mod map_entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::{collections::HashMap, hash::Hash};
+
+    pub fn serialize<K: Serialize, V: Serialize, S: Serializer>(
+        map: &HashMap<K, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+    }
+}
+
+// derived from the action types, so every type pair can be registered as its own asset type
 #[cfg(feature = "serialize")]
 impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> TypeUuid
     for ActionMap<TKeyAction, TAxisAction>
 {
-    const TYPE_UUID: bevy::reflect::Uuid = Uuid::from_u128(139351808413923814412416017277321670424);
+    const TYPE_UUID: bevy::reflect::Uuid = Uuid::from_u128(hash_type_names(
+        139351808413923814412416017277321670424,
+        &[
+            std::any::type_name::<TKeyAction>(),
+            std::any::type_name::<TAxisAction>(),
+        ],
+    ));
+}
+
+// 128 bit FNV-1a starting from `base`, the names are separated so they can't run into each other
+#[cfg(feature = "serialize")]
+const fn hash_type_names(base: u128, names: &[&str]) -> u128 {
+    const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+    let mut hash = base;
+    let mut i = 0;
+    while i < names.len() {
+        let bytes = names[i].as_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            hash = (hash ^ bytes[j] as u128).wrapping_mul(PRIME);
+            j += 1;
+        }
+        hash = (hash ^ 0xff).wrapping_mul(PRIME);
+        i += 1;
+    }
+    hash
 }
 
 impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> Default
@@ -180,6 +304,7 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> Default
             bound_keys: Default::default(),
             bound_key_combinations: Default::default(),
             bound_axes: Default::default(),
+            bound_touch_sticks: Default::default(),
         }
     }
 }
@@ -246,18 +371,29 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyActi
         key_action_bindings: KeyBindings<TKeyAction>,
         axis_action_bindings: AxisBindings<TAxisAction>,
     ) {
-        self.clear_bindings();
+        self.try_set_bindings(key_action_bindings, axis_action_bindings)
+            .expect("Bindings should be valid when set directly");
+    }
+
+    /// # Errors
+    ///
+    /// Will return an `Err` if there's a binding conflict, the current bindings are kept in that case
+    pub fn try_set_bindings(
+        &mut self,
+        key_action_bindings: KeyBindings<TKeyAction>,
+        axis_action_bindings: AxisBindings<TAxisAction>,
+    ) -> Result<(), BindingError> {
+        let mut map = Self::default();
 
         for action in key_action_bindings {
             for b in action.1 {
-                self.bind_button_combination_action_internal(action.0.value, b, action.0.id)
-                    .expect("Bindings should be valid when set directly");
+                map.bind_button_combination_action_internal(action.0.value, b, action.0.id)?;
             }
         }
 
         for action in axis_action_bindings {
             for b in action.1 {
-                self.bind_axis_with_deadzone_internal(
+                map.bind_axis_with_deadzone_internal(
                     action.0.value,
                     b.0,
                     b.1 as f32 / DEADZONE_PRECISION,
@@ -265,6 +401,9 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyActi
                 );
             }
         }
+
+        *self = map;
+        Ok(())
     }
 
     pub fn clear_bindings(&mut self) {
@@ -272,9 +411,58 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyActi
         self.axis_action_bindings = Default::default();
         self.bound_keys = Default::default();
         self.bound_axes = Default::default();
+        self.bound_touch_sticks = Default::default();
         self.bound_key_combinations = Default::default();
     }
 
+    /// # Errors
+    ///
+    /// Will return an `Err` if a default binding conflicts with the current bindings of other actions
+    pub fn reset_action_to_default<K: Into<TKeyAction>>(
+        &mut self,
+        action: K,
+        defaults: &Self,
+    ) -> Result<&mut Self, BindingError> {
+        let action = action.into();
+        let mut key_bindings = self.key_action_bindings.clone();
+        key_bindings.retain(|key, _| key.value != action);
+        key_bindings.extend(
+            defaults
+                .key_action_bindings
+                .iter()
+                .filter(|(key, _)| key.value == action)
+                .map(|(key, bindings)| (*key, bindings.clone())),
+        );
+
+        self.try_set_bindings(key_bindings, self.axis_action_bindings.clone())?;
+        Ok(self)
+    }
+
+    pub fn reset_axis_to_default<A: Into<TAxisAction>>(
+        &mut self,
+        action: A,
+        defaults: &Self,
+    ) -> &mut Self {
+        let action = action.into();
+        let mut axis_bindings = self.axis_action_bindings.clone();
+        axis_bindings.retain(|key, _| key.value != action);
+        axis_bindings.extend(
+            defaults
+                .axis_action_bindings
+                .iter()
+                .filter(|(key, _)| key.value == action)
+                .map(|(key, bindings)| (*key, bindings.clone())),
+        );
+
+        // key bindings are unchanged so there's nothing to conflict with
+        self.set_bindings(self.key_action_bindings.clone(), axis_bindings);
+        self
+    }
+
+    pub fn reset_all_to_defaults(&mut self, defaults: &Self) {
+        *self = defaults.clone();
+    }
+
     fn bind_button_action_internal<K: Into<TKeyAction>, B: Into<ButtonCode>>(
         &mut self,
         action: K,
@@ -361,6 +549,9 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyActi
                         self.bound_axes.insert(axis);
                     }
                 }
+                AxisBinding::TouchStickX(stick) | AxisBinding::TouchStickY(stick) => {
+                    self.bound_touch_sticks.insert(stick);
+                }
             }
 
             #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
@@ -374,18 +565,39 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyActi
 #[derive(Component)]
 pub struct ActionInput<TKeyAction, TAxisAction = NoAxis> {
     pub(crate) button_states: HashMap<DeviceData<ButtonCode>, Option<ButtonState>>,
-    button_actions: HashMap<PlayerData<TKeyAction>, ActionState>,
+    // the buttons that changed this frame, by when the device event arrived
+    pub(crate) button_timestamps: HashMap<DeviceData<ButtonCode>, InputTimestamp>,
+    // buttons held across a window focus change, ignored until they're released
+    pub(crate) suppressed_buttons: HashSet<DeviceData<ButtonCode>>,
+    pub(crate) button_actions: HashMap<PlayerData<TKeyAction>, ActionState>,
+    pub(crate) press_timestamps: HashMap<PlayerData<TKeyAction>, InputTimestamp>,
+    // the clock of snapshot driven transitions
+    pub(crate) snapshot_seconds: f64,
     gamepad_axes_values: HashMap<PlayerData<GamepadAxisType>, f32>,
-    axes: HashMap<PlayerData<TAxisAction>, f32>,
+    pub(crate) axes: HashMap<PlayerData<TAxisAction>, f32>,
+    pub(crate) touch_stick_anchors: HashMap<TouchStick, (u64, Vec2)>,
+    pub(crate) touch_stick_values: HashMap<TouchStick, Vec2>,
+    pub(crate) pointer: Pointer,
+    pub(crate) last_device: Option<InputDevice>,
+    pub(crate) reported_device: Option<InputDevice>,
 }
 
 impl<TKeyAction, TAxisAction> Default for ActionInput<TKeyAction, TAxisAction> {
     fn default() -> Self {
         Self {
             button_states: Default::default(),
+            button_timestamps: Default::default(),
+            suppressed_buttons: Default::default(),
             button_actions: Default::default(),
+            press_timestamps: Default::default(),
+            snapshot_seconds: 0.,
             gamepad_axes_values: Default::default(),
             axes: Default::default(),
+            touch_stick_anchors: Default::default(),
+            touch_stick_values: Default::default(),
+            pointer: Default::default(),
+            last_device: None,
+            reported_device: None,
         }
     }
 }
@@ -418,6 +630,19 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionInput<TKeyAc
         self.button_actions.insert(button.into(), ActionState::Used);
     }
 
+    // when the action was pressed, for as long as it's pressed, held or just released
+    pub fn pressed_at(&self, button: TKeyAction) -> Option<InputTimestamp> {
+        let action_data = button.into();
+        match self.get_action_state(&action_data)? {
+            ActionState::Pressed => self.press_timestamps.get(&action_data).copied(),
+            state => state.pressed_at(),
+        }
+    }
+
+    pub fn released_at(&self, button: TKeyAction) -> Option<InputTimestamp> {
+        self.get_button_action_state(button)?.released_at()
+    }
+
     pub fn get_axis(&self, axis: &TAxisAction) -> f32 {
         if let Some(axis_value) = self.axes.get(&PlayerData::new(*axis)) {
             *axis_value
@@ -452,7 +677,115 @@ impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionInput<TKeyAc
         }
     }
 
-    fn button_is_pressed_or_held(&self, button_data: &PlayerData<ButtonCode>) -> bool {
+    // drops the state of actions whose bindings differ between the maps
+    #[cfg(feature = "serialize")]
+    pub(crate) fn clear_rebound_actions(
+        &mut self,
+        old_map: &ActionMap<TKeyAction, TAxisAction>,
+        new_map: &ActionMap<TKeyAction, TAxisAction>,
+    ) {
+        self.button_actions.retain(|action, _| {
+            old_map.key_action_bindings.get(action) == new_map.key_action_bindings.get(action)
+        });
+        self.axes.retain(|axis, _| {
+            old_map.axis_action_bindings.get(axis) == new_map.axis_action_bindings.get(axis)
+        });
+    }
+
+    // `Pressed` if any binding was just completed, `Held` if any is still down
+    fn bound_action_state(
+        &self,
+        action_data: &PlayerData<TKeyAction>,
+        bindings: &[KeyActionBinding],
+    ) -> Option<ButtonState> {
+        let mut state = None;
+
+        'bindings: for binding_keys in bindings {
+            let mut just_pressed_at_least_one_key = false;
+
+            for k in binding_keys {
+                match self.button_states.get(&k.player_data(action_data.id)) {
+                    Some(Some(ButtonState::Pressed)) => just_pressed_at_least_one_key = true,
+                    Some(Some(ButtonState::Held)) => {}
+                    _ => continue 'bindings,
+                }
+            }
+
+            // at least one 1 key was just pressed, the rest can be held
+            if just_pressed_at_least_one_key {
+                return Some(ButtonState::Pressed);
+            }
+
+            state = Some(ButtonState::Held);
+        }
+
+        state
+    }
+
+    // the latest transition of the bound buttons, the frame time if none reported one
+    fn bound_action_timestamp(
+        &self,
+        action_data: &PlayerData<TKeyAction>,
+        bindings: &[KeyActionBinding],
+        now: InputTimestamp,
+    ) -> InputTimestamp {
+        bindings
+            .iter()
+            .flatten()
+            .filter_map(|k| self.button_timestamps.get(&k.player_data(action_data.id)))
+            .copied()
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .unwrap_or(now)
+    }
+
+    pub(crate) fn update_action_state(
+        &mut self,
+        action_data: PlayerData<TKeyAction>,
+        state: Option<ButtonState>,
+        delta_seconds: f32,
+        timestamp: InputTimestamp,
+    ) {
+        let current_state = self.get_action_state(&action_data);
+        let current_duration = current_state.unwrap_or(&ActionState::Used).duration();
+        match current_state {
+            None | Some(ActionState::Released(..) | ActionState::Used) => {
+                if state == Some(ButtonState::Pressed) {
+                    self.button_actions
+                        .insert(action_data, ActionState::Pressed);
+                    self.press_timestamps.insert(action_data, timestamp);
+                } else {
+                    self.button_actions.remove(&action_data);
+                    self.press_timestamps.remove(&action_data);
+                }
+            }
+            Some(ActionState::Pressed | ActionState::Held(..)) => {
+                let mut data = ActiveKeyData {
+                    duration: current_duration + delta_seconds,
+                    pressed_at: self
+                        .press_timestamps
+                        .get(&action_data)
+                        .copied()
+                        .unwrap_or_default(),
+                    released_at: None,
+                };
+                let state = if state.is_some() {
+                    ActionState::Held(data)
+                } else {
+                    data.released_at = Some(timestamp);
+                    ActionState::Released(data)
+                };
+                self.button_actions.insert(action_data, state);
+            }
+        }
+    }
+
+    pub(crate) fn clear_device_axes(&mut self) {
+        self.gamepad_axes_values.clear();
+        self.touch_stick_anchors.clear();
+        self.touch_stick_values.clear();
+    }
+
+    pub(crate) fn button_is_pressed_or_held(&self, button_data: &PlayerData<ButtonCode>) -> bool {
         matches!(
             self.button_states.get(button_data),
             Some(Some(ButtonState::Pressed | ButtonState::Held))
@@ -474,18 +807,35 @@ pub(crate) fn handle_keyboard_input<
         &mut ActionInput<TKeyAction, TAxisAction>,
     )>,
     kb_input: Res<Input<KeyCode>>,
+    mut kb_events: EventReader<KeyboardInput>,
+    mut scan_code_input: Local<Input<u32>>,
+    settings: Res<ButtonStateSettings>,
 ) {
+    scan_code_input.clear();
+    for ev in kb_events.iter() {
+        match ev.state {
+            ElementState::Pressed => scan_code_input.press(ev.scan_code),
+            ElementState::Released => scan_code_input.release(ev.scan_code),
+        }
+    }
+
+    if settings.event_driven {
+        return;
+    }
+
     for (map, mut input) in input_q.iter_mut() {
         for btn_data in &map.bound_keys {
-            if let PlayerData {
-                value: ButtonCode::Kb(key),
-                ..
-            } = btn_data
-            {
-                input
-                    .button_states
-                    .insert(*btn_data, get_button_state(&kb_input, key));
+            let state = match btn_data.value {
+                ButtonCode::Kb(key) => get_button_state(&kb_input, &key),
+                ButtonCode::ScanCode(scan_code) => get_button_state(&scan_code_input, &scan_code),
+                _ => continue,
+            };
+
+            if state == Some(ButtonState::Pressed) {
+                input.last_device = Some(InputDevice::Keyboard);
             }
+
+            input.button_states.insert(*btn_data, state);
         }
     }
 }
@@ -499,7 +849,12 @@ pub(crate) fn handle_mouse_input<
         &mut ActionInput<TKeyAction, TAxisAction>,
     )>,
     mouse_input: Res<Input<MouseButton>>,
+    settings: Res<ButtonStateSettings>,
 ) {
+    if settings.event_driven {
+        return;
+    }
+
     for (map, mut input) in input_q.iter_mut() {
         for btn_data in &map.bound_keys {
             if let PlayerData {
@@ -507,9 +862,13 @@ pub(crate) fn handle_mouse_input<
                 ..
             } = btn_data
             {
-                input
-                    .button_states
-                    .insert(*btn_data, get_button_state(&mouse_input, button));
+                let state = get_button_state(&mouse_input, button);
+
+                if state == Some(ButtonState::Pressed) {
+                    input.last_device = Some(InputDevice::Mouse);
+                }
+
+                input.button_states.insert(*btn_data, state);
             }
         }
     }
@@ -527,13 +886,19 @@ pub(crate) fn handle_gamepad_events<
         Option<&InputGamepad>,
     )>,
     gamepad_input: Res<Input<GamepadButton>>,
+    device_settings: Res<ActiveDeviceSettings>,
+    settings: Res<ButtonStateSettings>,
 ) {
     let all_events: Vec<_> = gamepad_events.iter().collect();
 
     for (map, mut input, pad_id) in input_q.iter_mut() {
         for event in all_events.iter() {
             match event {
-                GamepadEvent(gamepad, GamepadEventType::ButtonChanged(button, _strength)) => {
+                GamepadEvent(gamepad, GamepadEventType::ButtonChanged(button, strength)) => {
+                    if settings.event_driven {
+                        continue;
+                    }
+
                     if let Some(pad_id) = pad_id {
                         if pad_id.pad_id != gamepad.0 {
                             // pad id doesn't match - skip to next id
@@ -543,6 +908,10 @@ pub(crate) fn handle_gamepad_events<
 
                     let input_code = ButtonCode::Gamepad(*button);
                     if map.bound_keys.get(&input_code.player_data(None)).is_some() {
+                        if *strength > device_settings.axis_noise_threshold {
+                            input.last_device = Some(InputDevice::Gamepad(gamepad.0));
+                        }
+
                         input.button_states.insert(
                             input_code.player_data(None),
                             get_button_state(&gamepad_input, &GamepadButton(*gamepad, *button)),
@@ -558,6 +927,10 @@ pub(crate) fn handle_gamepad_events<
                     }
 
                     if map.bound_axes.get(axis_type).is_some() {
+                        if strength.abs() > device_settings.axis_noise_threshold {
+                            input.last_device = Some(InputDevice::Gamepad(gamepad.0));
+                        }
+
                         input.gamepad_axes_values.insert(
                             DeviceData {
                                 value: *axis_type,
@@ -580,83 +953,49 @@ pub(crate) fn process_button_actions<
     mut input_q: Query<(
         &ActionMap<TKeyAction, TAxisAction>,
         &mut ActionInput<TKeyAction, TAxisAction>,
+        Option<&mut InjectedInput<TKeyAction, TAxisAction>>,
+        Option<&InputMuted<TKeyAction, TAxisAction>>,
     )>,
-    time: Res<Time>,
+    global_mute: Option<Res<InputMuted<TKeyAction, TAxisAction>>>,
+    time: Res<InputTime>,
 ) {
-    for (map, mut input) in input_q.iter_mut() {
-        'actions: for (action_data, bindings) in &map.key_action_bindings {
-            let current_state = input.get_action_state(action_data);
-            let current_duration = current_state.unwrap_or(&ActionState::Used).duration();
-            match current_state {
-                None | Some(ActionState::Released(..) | ActionState::Used) => {
-                    'bindings: for binding_keys in bindings {
-                        let mut just_pressed_at_least_one_key = false;
-
-                        for k in binding_keys.iter() {
-                            if let Some(key_state) =
-                                input.button_states.get(&k.player_data(action_data.id))
-                            {
-                                match key_state {
-                                    Some(ButtonState::Pressed) => {
-                                        just_pressed_at_least_one_key = true;
-                                        continue;
-                                    }
-                                    Some(ButtonState::Held) => {
-                                        continue;
-                                    }
-                                    _ => continue 'bindings,
-                                }
-                            }
-
-                            continue 'bindings;
-                        }
-
-                        // at least one 1 key was just pressed, the rest can be held
-                        if just_pressed_at_least_one_key {
-                            input
-                                .button_actions
-                                .insert(*action_data, ActionState::Pressed);
-                            continue 'actions;
-                        }
-                    }
-
-                    input.button_actions.remove(action_data);
-                }
-                Some(ActionState::Pressed | ActionState::Held(..)) => {
-                    // check if all keys are still held
-                    'held_bindings: for binding_keys in bindings {
-                        for k in binding_keys.iter() {
-                            if let Some(key_state) =
-                                input.button_states.get(&k.player_data(action_data.id))
-                            {
-                                match key_state {
-                                    Some(ButtonState::Pressed | ButtonState::Held) => {
-                                        continue;
-                                    }
-                                    _ => continue 'held_bindings,
-                                }
-                            }
+    for (map, mut input, mut injected, mute) in input_q.iter_mut() {
+        let mutes = [mute, global_mute.as_deref()];
+        for (action_data, bindings) in &map.key_action_bindings {
+            let mut state = if is_action_muted(&mutes, action_data.value) {
+                None
+            } else {
+                input.bound_action_state(action_data, bindings)
+            };
+            if let Some(injected) = &injected {
+                state = injected.merge_button(action_data, state);
+            }
 
-                            continue 'held_bindings;
-                        }
+            let timestamp = input.bound_action_timestamp(action_data, bindings, time.now());
+            input.update_action_state(*action_data, state, time.delta_seconds(), timestamp);
+        }
 
-                        input.button_actions.insert(
-                            *action_data,
-                            ActionState::Held(ActiveKeyData {
-                                duration: current_duration + time.delta_seconds(),
-                            }),
-                        );
-                        continue 'actions;
-                    }
+        // actions without bindings are only driven by injected input
+        let unbound_actions: HashSet<_> = input
+            .button_actions
+            .keys()
+            .chain(
+                injected
+                    .iter()
+                    .flat_map(|injected| injected.injected_actions()),
+            )
+            .filter(|action_data| !map.key_action_bindings.contains_key(action_data))
+            .copied()
+            .collect();
+        for action_data in unbound_actions {
+            let state = injected
+                .as_ref()
+                .and_then(|injected| injected.merge_button(&action_data, None));
+            input.update_action_state(action_data, state, time.delta_seconds(), time.now());
+        }
 
-                    input.button_actions.insert(
-                        *action_data,
-                        ActionState::Released(ActiveKeyData {
-                            duration: current_duration + time.delta_seconds(),
-                        }),
-                    );
-                }
-            }
+        if let Some(injected) = &mut injected {
+            injected.advance_buttons();
         }
     }
 }
@@ -668,10 +1007,22 @@ pub(crate) fn process_axis_actions<
     mut input_q: Query<(
         &ActionMap<TKeyAction, TAxisAction>,
         &mut ActionInput<TKeyAction, TAxisAction>,
+        Option<&mut InjectedInput<TKeyAction, TAxisAction>>,
+        Option<&InputMuted<TKeyAction, TAxisAction>>,
     )>,
+    global_mute: Option<Res<InputMuted<TKeyAction, TAxisAction>>>,
 ) {
-    for (map, mut input) in input_q.iter_mut() {
+    for (map, mut input, injected, mute) in input_q.iter_mut() {
+        let mutes = [mute, global_mute.as_deref()];
         for (axis_action_data, bindings) in &map.axis_action_bindings {
+            if is_axis_muted(&mutes, axis_action_data.value) {
+                let axis_value = injected
+                    .as_ref()
+                    .map_or(0., |injected| injected.merge_axis(axis_action_data, 0.));
+                input.axes.insert(*axis_action_data, axis_value);
+                continue;
+            }
+
             let axis_value = bindings
                 .iter()
                 .map(|b| {
@@ -702,6 +1053,14 @@ pub(crate) fn process_axis_actions<
                                 *deadzone,
                             )
                         }
+                        (AxisBinding::TouchStickX(stick), deadzone) => (
+                            input.touch_stick_values.get(stick).map_or(0., |v| v.x),
+                            *deadzone,
+                        ),
+                        (AxisBinding::TouchStickY(stick), deadzone) => (
+                            input.touch_stick_values.get(stick).map_or(0., |v| v.y),
+                            *deadzone,
+                        ),
                     };
 
                     if deadzone == 0 {
@@ -719,17 +1078,39 @@ pub(crate) fn process_axis_actions<
                 })
                 .fold(0., |a: f32, b: f32| if a.abs() > b.abs() { a } else { b });
 
+            let axis_value = injected.as_ref().map_or(axis_value, |injected| {
+                injected.merge_axis(axis_action_data, axis_value)
+            });
             input.axes.insert(*axis_action_data, axis_value);
         }
+
+        input
+            .axes
+            .retain(|axis, _| map.axis_action_bindings.contains_key(axis));
+        if let Some(mut injected) = injected {
+            for axis in injected.injected_axes() {
+                if !map.axis_action_bindings.contains_key(axis) {
+                    input.axes.insert(*axis, injected.merge_axis(axis, 0.));
+                }
+            }
+
+            injected.advance_axes();
+        }
     }
 }
 
+// profile entities only hold bindings
+#[cfg(feature = "serialize")]
+type ProfileFilter = Without<crate::BindingsProfile>;
+#[cfg(not(feature = "serialize"))]
+type ProfileFilter = ();
+
 pub(crate) fn add_input<
     TKeyAction: ActionMapInput + 'static,
     TAxisAction: ActionMapInput + 'static,
 >(
     mut commands: Commands,
-    map_q: Query<Entity, Added<ActionMap<TKeyAction, TAxisAction>>>,
+    map_q: Query<Entity, (Added<ActionMap<TKeyAction, TAxisAction>>, ProfileFilter)>,
 ) {
     for map_e in map_q.iter() {
         commands
@@ -749,3 +1130,79 @@ fn get_button_state<T: Copy + Eq + Hash>(input: &Input<T>, button: &T) -> Option
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestMap},
+        ButtonCode,
+    };
+    use bevy::{
+        ecs::event::Events,
+        input::{keyboard::KeyboardInput, ElementState},
+        prelude::*,
+    };
+
+    fn setup_app(map: TestMap) -> (App, Entity) {
+        let mut app = test_app();
+        let e = spawn_player(&mut app, map);
+        // first update adds the ActionInput component
+        app.update();
+        (app, e)
+    }
+
+    fn send_scan_code(app: &mut App, scan_code: u32, state: ElementState) {
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code,
+                key_code: None,
+                state,
+            });
+    }
+
+    #[test]
+    fn reset_to_defaults() {
+        let mut defaults = TestMap::default();
+        defaults
+            .bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::LControl)
+            .unwrap();
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::W)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::Space)
+            .unwrap();
+
+        // space is still used by shoot
+        assert!(map
+            .reset_action_to_default(TestAction::Jump, &defaults)
+            .is_err());
+        map.reset_action_to_default(TestAction::Shoot, &defaults)
+            .unwrap()
+            .reset_action_to_default(TestAction::Jump, &defaults)
+            .unwrap();
+        assert_eq!(defaults.get_key_bindings(), map.get_key_bindings());
+
+        map.clear_bindings();
+        map.reset_all_to_defaults(&defaults);
+        assert_eq!(defaults.get_key_bindings(), map.get_key_bindings());
+    }
+
+    #[test]
+    fn scan_code_binding() {
+        let (mut app, e) = setup_app(test_map([(TestAction::Jump, ButtonCode::ScanCode(57))], []));
+
+        send_scan_code(&mut app, 57, ElementState::Pressed);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Jump));
+
+        app.update();
+        assert!(input(&app, e).held(TestAction::Jump));
+
+        send_scan_code(&mut app, 57, ElementState::Released);
+        app.update();
+        assert!(input(&app, e).just_released(TestAction::Jump));
+    }
+}
@@ -1,4 +1,15 @@
-use crate::action_map::add_input;
+use crate::{
+    action_events::{emit_action_events, ActionEvent},
+    action_map::add_input,
+    button_events::{handle_button_events, ButtonStateSettings},
+    device::{emit_active_device_changes, ActiveDeviceChanged, ActiveDeviceSettings},
+    fixed_timestep::latch_fixed_input,
+    focus::{handle_window_focus, WindowFocusSettings},
+    input_time::{sync_input_time, InputTime},
+    pointer::handle_pointer_input,
+    remote::apply_remote_input,
+    touch::{handle_touch_input, TouchGestureSettings},
+};
 #[cfg(feature = "serialize")]
 use crate::{
     action_map::{
@@ -6,15 +17,26 @@ use crate::{
         process_button_actions, ActionInput, ActionMap, ActionMapInput,
     },
     bindings_loader::{
-        load_map, process_map_event, save_map, ActionMapLoad, ActionMapSave, MapIoRequest,
+        process_map_io, report_unclaimed_map_io, MapIoClaims, MapIoQueue, MapIoRequest,
+        MapIoSettings,
+    },
+    map_asset::{
+        apply_map_assets, claim_map_extensions, sync_map_loader_config, ActionMapLoader,
+        MapLoaderConfig,
     },
-    MapIoEvent,
+    profiles::{
+        apply_profiles, process_profile_requests, BindingsProfiles, ProfileActions, ProfileEvent,
+        ProfileQueue, ProfileRequest,
+    },
+    recording::{record_input, replay_input},
+    versioning::BindingsMigrations,
+    BindingsPaths, MapIoEvent, SerializableActionInput,
 };
 use bevy::{input::InputSystem, prelude::*};
-#[cfg(feature = "serialize")]
-use bevy_extensions::panic_on_error;
 
 pub const PROCESS_INPUT_LABEL: &str = "UPDATE_STATES";
+// button and axis actions are resolved from the updated states
+pub const PROCESS_ACTIONS_LABEL: &str = "UPDATE_ACTIONS";
 
 #[cfg(not(feature = "serialize"))]
 use crate::action_map::{
@@ -22,7 +44,15 @@ use crate::action_map::{
     process_button_actions, ActionInput, ActionMap, ActionMapInput,
 };
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg(feature = "serialize")]
+const REPLAY_LABEL: &str = "REPLAY_ACTIONS";
+#[cfg(feature = "serialize")]
+const MAP_IO_LABEL: &str = "PROCESS_MAP_IO";
+const LATCH_FIXED_LABEL: &str = "LATCH_FIXED_ACTIONS";
+const REMOTE_LABEL: &str = "APPLY_REMOTE_ACTIONS";
+const ACTION_EVENTS_LABEL: &str = "EMIT_ACTION_EVENTS";
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct NoAxis;
 
@@ -38,6 +68,20 @@ pub trait AppActionInputExt {
         ActionMap<TKeyAction, TAxisAction>: Default,
         TKeyAction: ActionMapInput + 'static,
         TAxisAction: ActionMapInput + 'static;
+
+    // bindings files, recordings and map assets, profiles only for the first action types added
+    #[cfg(feature = "serialize")]
+    fn add_action_serialize_systems<TKeyAction>(&mut self) -> &mut Self
+    where
+        ActionMap<TKeyAction, NoAxis>: Default,
+        TKeyAction: SerializableActionInput + 'static;
+
+    #[cfg(feature = "serialize")]
+    fn add_action_serialize_systems_with_axis<TKeyAction, TAxisAction>(&mut self) -> &mut Self
+    where
+        ActionMap<TKeyAction, TAxisAction>: Default,
+        TKeyAction: SerializableActionInput + 'static,
+        TAxisAction: SerializableActionInput + 'static;
 }
 
 impl AppActionInputExt for App {
@@ -47,46 +91,92 @@ impl AppActionInputExt for App {
         TKeyAction: ActionMapInput + 'static,
         TAxisAction: ActionMapInput + 'static,
     {
-        self.add_system_to_stage(CoreStage::Last, add_input::<TKeyAction, TAxisAction>)
+        self.init_resource::<TouchGestureSettings>()
+            .init_resource::<ActiveDeviceSettings>()
+            .init_resource::<InputTime>()
+            .init_resource::<ButtonStateSettings>()
+            .init_resource::<WindowFocusSettings>()
+            .add_event::<ActiveDeviceChanged>()
+            .add_event::<ActionEvent<TKeyAction>>()
+            .add_system_to_stage(CoreStage::Last, add_input::<TKeyAction, TAxisAction>)
             .add_system_set_to_stage(
                 CoreStage::PreUpdate,
                 SystemSet::new()
+                    .with_system(sync_input_time.before(PROCESS_INPUT_LABEL))
+                    // the handlers all write the last used device, they run in a fixed order so the
+                    // device winning within a frame doesn't change from one frame to the next
                     .with_system(
                         handle_keyboard_input::<TKeyAction, TAxisAction>
                             .label(PROCESS_INPUT_LABEL)
                             .after(InputSystem),
                     )
+                    .with_system(
+                        handle_button_events::<TKeyAction, TAxisAction>
+                            .label(PROCESS_INPUT_LABEL)
+                            .after(InputSystem)
+                            .after(handle_keyboard_input::<TKeyAction, TAxisAction>),
+                    )
                     .with_system(
                         handle_mouse_input::<TKeyAction, TAxisAction>
                             .label(PROCESS_INPUT_LABEL)
-                            .after(InputSystem),
+                            .after(InputSystem)
+                            .after(handle_button_events::<TKeyAction, TAxisAction>),
+                    )
+                    .with_system(
+                        handle_pointer_input::<TKeyAction, TAxisAction>
+                            .label(PROCESS_INPUT_LABEL)
+                            .after(InputSystem)
+                            .after(handle_mouse_input::<TKeyAction, TAxisAction>),
                     )
                     .with_system(
                         handle_gamepad_events::<TKeyAction, TAxisAction>
                             .label(PROCESS_INPUT_LABEL)
-                            .after(InputSystem),
+                            .after(InputSystem)
+                            .after(handle_pointer_input::<TKeyAction, TAxisAction>),
+                    )
+                    .with_system(
+                        handle_touch_input::<TKeyAction, TAxisAction>
+                            .label(PROCESS_INPUT_LABEL)
+                            .after(InputSystem)
+                            .after(handle_gamepad_events::<TKeyAction, TAxisAction>),
+                    )
+                    .with_system(
+                        handle_window_focus::<TKeyAction, TAxisAction>
+                            .after(PROCESS_INPUT_LABEL)
+                            .before(PROCESS_ACTIONS_LABEL),
                     )
                     .with_system(
                         process_button_actions::<TKeyAction, TAxisAction>
+                            .label(PROCESS_ACTIONS_LABEL)
                             .after(PROCESS_INPUT_LABEL),
                     )
                     .with_system(
-                        process_axis_actions::<TKeyAction, TAxisAction>.after(PROCESS_INPUT_LABEL),
+                        process_axis_actions::<TKeyAction, TAxisAction>
+                            .label(PROCESS_ACTIONS_LABEL)
+                            .after(PROCESS_INPUT_LABEL),
+                    )
+                    .with_system(
+                        emit_active_device_changes::<TKeyAction, TAxisAction>
+                            .after(PROCESS_INPUT_LABEL),
+                    )
+                    .with_system(
+                        apply_remote_input::<TKeyAction, TAxisAction>
+                            .label(REMOTE_LABEL)
+                            .after(PROCESS_ACTIONS_LABEL),
+                    )
+                    .with_system(
+                        latch_fixed_input::<TKeyAction, TAxisAction>
+                            .label(LATCH_FIXED_LABEL)
+                            .after(PROCESS_ACTIONS_LABEL)
+                            .after(REMOTE_LABEL),
+                    )
+                    .with_system(
+                        emit_action_events::<TKeyAction, TAxisAction>
+                            .label(ACTION_EVENTS_LABEL)
+                            .after(PROCESS_ACTIONS_LABEL)
+                            .after(REMOTE_LABEL),
                     ),
-            );
-
-        #[cfg(feature = "serialize")]
-        {
-            self.insert_resource(ActionMapLoad::<TKeyAction, TAxisAction>(None))
-                .insert_resource(ActionMapSave(None))
-                .add_event::<MapIoRequest>()
-                .add_event::<MapIoEvent>()
-                .add_system(process_map_event::<TKeyAction, TAxisAction>)
-                .add_system(load_map::<TKeyAction, TAxisAction>.chain(panic_on_error))
-                .add_system(save_map.chain(panic_on_error));
-        }
-
-        self
+            )
     }
 
     fn add_action_input_systems<TKeyAction>(&mut self) -> &mut Self
@@ -96,4 +186,99 @@ impl AppActionInputExt for App {
     {
         self.add_action_input_systems_with_axis::<TKeyAction, NoAxis>()
     }
+
+    #[cfg(feature = "serialize")]
+    fn add_action_serialize_systems<TKeyAction>(&mut self) -> &mut Self
+    where
+        ActionMap<TKeyAction>: Default,
+        TKeyAction: SerializableActionInput + 'static,
+    {
+        self.add_action_serialize_systems_with_axis::<TKeyAction, NoAxis>()
+    }
+
+    #[cfg(feature = "serialize")]
+    fn add_action_serialize_systems_with_axis<TKeyAction, TAxisAction>(&mut self) -> &mut Self
+    where
+        ActionMap<TKeyAction, TAxisAction>: Default,
+        TKeyAction: SerializableActionInput + 'static,
+        TAxisAction: SerializableActionInput + 'static,
+    {
+        // shared by all action types, so only added by the first one
+        if !self.world.contains_resource::<MapIoClaims>() {
+            self.init_resource::<MapIoClaims>()
+                .add_system(report_unclaimed_map_io.after(MAP_IO_LABEL));
+        }
+
+        self.init_resource::<MapIoQueue<TKeyAction, TAxisAction>>()
+            .init_resource::<MapIoSettings>()
+            .init_resource::<BindingsMigrations>()
+            .init_resource::<BindingsPaths>()
+            .add_event::<MapIoRequest>()
+            .add_event::<MapIoEvent>()
+            .add_system(process_map_io::<TKeyAction, TAxisAction>.label(MAP_IO_LABEL))
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                SystemSet::new()
+                    .with_system(
+                        replay_input::<TKeyAction, TAxisAction>
+                            .label(REPLAY_LABEL)
+                            .after(PROCESS_ACTIONS_LABEL)
+                            .before(LATCH_FIXED_LABEL)
+                            .before(ACTION_EVENTS_LABEL),
+                    )
+                    .with_system(
+                        record_input::<TKeyAction, TAxisAction>
+                            .after(PROCESS_ACTIONS_LABEL)
+                            .after(REPLAY_LABEL)
+                            .after(REMOTE_LABEL),
+                    ),
+            );
+
+        let type_name = std::any::type_name::<ActionMap<TKeyAction, TAxisAction>>();
+        if let Some(owner) = self.world.get_resource::<ProfileActions>() {
+            info!(
+                "bindings profiles hold `{}`, not `{}` added later",
+                owner.0, type_name
+            );
+        } else {
+            self.insert_resource(ProfileActions(type_name))
+                .init_resource::<BindingsProfiles>()
+                .init_resource::<ProfileQueue>()
+                .add_event::<ProfileRequest>()
+                .add_event::<ProfileEvent>()
+                .add_system_set_to_stage(
+                    CoreStage::PreUpdate,
+                    SystemSet::new()
+                        .with_system(process_profile_requests::<TKeyAction, TAxisAction>)
+                        .with_system(
+                            apply_profiles::<TKeyAction, TAxisAction>.before(PROCESS_INPUT_LABEL),
+                        ),
+                );
+        }
+
+        // maps can only be loaded as assets when the `AssetPlugin` has been added before, which
+        // headless apps usually don't need
+        if self.world.contains_resource::<AssetServer>() {
+            claim_map_extensions::<TKeyAction, TAxisAction>(&mut self.world);
+            self.add_asset::<ActionMap<TKeyAction, TAxisAction>>()
+                .init_resource::<MapLoaderConfig<TKeyAction, TAxisAction>>()
+                .init_asset_loader::<ActionMapLoader<TKeyAction, TAxisAction>>()
+                .add_system_to_stage(
+                    CoreStage::PreUpdate,
+                    sync_map_loader_config::<TKeyAction, TAxisAction>,
+                )
+                .add_system_to_stage(
+                    CoreStage::PreUpdate,
+                    apply_map_assets::<TKeyAction, TAxisAction>.before(PROCESS_INPUT_LABEL),
+                );
+        } else {
+            warn!(
+                "`.bindings` assets of `{}` can't be loaded, add the `AssetPlugin` \
+                (or `DefaultPlugins`) before calling `add_action_serialize_systems`",
+                std::any::type_name::<TKeyAction>()
+            );
+        }
+
+        self
+    }
 }
@@ -0,0 +1,250 @@
+// text form of bindings used in bindings files, e.g. `Ctrl+Mouse:Left` or `Pad:LeftStickX`
+use crate::{action_map::KeyActionBinding, AxisBinding, ButtonCode, TouchRegion, TouchStick};
+use bevy::prelude::*;
+use serde::{de::IntoDeserializer, Serialize};
+
+const COMBINATION_SEPARATOR: char = '+';
+const AXIS_SEPARATOR: char = '/';
+// modifiers are written first and without the left/right prefix
+const MODIFIERS: [(KeyCode, &str); 3] = [
+    (KeyCode::LControl, "Ctrl"),
+    (KeyCode::LShift, "Shift"),
+    (KeyCode::LAlt, "Alt"),
+];
+
+// the part of a binding that couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TokenError {
+    pub(crate) token: String,
+    pub(crate) reason: String,
+}
+
+impl TokenError {
+    fn new(token: &str, reason: impl Into<String>) -> Self {
+        Self {
+            token: token.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+// name of a unit variant
+pub(crate) fn variant_name<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => Some(name),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_variant<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    let de: serde::de::value::StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+    T::deserialize(de).ok()
+}
+
+pub(crate) fn format_button(button: ButtonCode) -> String {
+    match button {
+        ButtonCode::Kb(key) => MODIFIERS
+            .iter()
+            .find(|(modifier, _)| *modifier == key)
+            .map(|(_, name)| (*name).to_string())
+            .or_else(|| variant_name(&key))
+            .unwrap_or_else(|| format!("{:?}", key)),
+        ButtonCode::ScanCode(scan_code) => format!("Scan:{}", scan_code),
+        ButtonCode::Gamepad(button) => format!(
+            "Pad:{}",
+            variant_name(&button).unwrap_or_else(|| format!("{:?}", button))
+        ),
+        ButtonCode::Mouse(MouseButton::Other(button)) => format!("Mouse:{}", button),
+        ButtonCode::Mouse(button) => format!(
+            "Mouse:{}",
+            variant_name(&button).unwrap_or_else(|| format!("{:?}", button))
+        ),
+        ButtonCode::Touch(region) => format!("Touch:{}", format_region(region)),
+        ButtonCode::Gesture(gesture) => format!(
+            "Gesture:{}",
+            variant_name(&gesture).unwrap_or_else(|| format!("{:?}", gesture))
+        ),
+    }
+}
+
+pub(crate) fn parse_button(token: &str) -> Result<ButtonCode, TokenError> {
+    let token = token.trim();
+    let (device, name) = token.split_once(':').unwrap_or(("Key", token));
+
+    let button = match device {
+        "Key" => MODIFIERS
+            .iter()
+            .find(|(_, modifier)| *modifier == name)
+            .map(|(key, _)| *key)
+            .or_else(|| parse_variant(name))
+            .map(ButtonCode::Kb),
+        "Scan" => name.parse().ok().map(ButtonCode::ScanCode),
+        "Pad" => parse_variant(name).map(ButtonCode::Gamepad),
+        "Mouse" => name
+            .parse()
+            .ok()
+            .map(MouseButton::Other)
+            .or_else(|| parse_variant(name))
+            .map(ButtonCode::Mouse),
+        "Touch" => parse_region(name).map(ButtonCode::Touch),
+        "Gesture" => parse_variant(name).map(ButtonCode::Gesture),
+        _ => {
+            return Err(TokenError::new(
+                token,
+                format!("unknown device `{}`", device),
+            ))
+        }
+    };
+
+    button.ok_or_else(|| TokenError::new(token, format!("unknown {} button", device)))
+}
+
+pub(crate) fn format_binding(binding: &KeyActionBinding) -> String {
+    let mut modifiers: Vec<_> = MODIFIERS
+        .iter()
+        .filter(|(key, _)| binding.contains(&ButtonCode::Kb(*key)))
+        .map(|(_, name)| (*name).to_string())
+        .collect();
+    let mut buttons: Vec<_> = binding
+        .iter()
+        .filter(|b| !MODIFIERS.iter().any(|(key, _)| **b == ButtonCode::Kb(*key)))
+        .map(|b| format_button(*b))
+        .collect();
+    buttons.sort();
+    modifiers.append(&mut buttons);
+    modifiers.join(&COMBINATION_SEPARATOR.to_string())
+}
+
+pub(crate) fn parse_binding(text: &str) -> Result<KeyActionBinding, TokenError> {
+    if text.trim().is_empty() {
+        return Err(TokenError::new(text, "empty binding"));
+    }
+
+    text.split(COMBINATION_SEPARATOR)
+        .map(parse_button)
+        .collect()
+}
+
+pub(crate) fn format_axis(binding: &AxisBinding) -> String {
+    match binding {
+        AxisBinding::Buttons(neg, pos) => {
+            format!(
+                "{}{}{}",
+                format_button(*neg),
+                AXIS_SEPARATOR,
+                format_button(*pos)
+            )
+        }
+        AxisBinding::GamepadAxis(axis) => format!(
+            "Pad:{}",
+            variant_name(axis).unwrap_or_else(|| format!("{:?}", axis))
+        ),
+        AxisBinding::TouchStickX(stick) => format!("TouchStickX:{}", format_stick(*stick)),
+        AxisBinding::TouchStickY(stick) => format!("TouchStickY:{}", format_stick(*stick)),
+    }
+}
+
+pub(crate) fn parse_axis(text: &str) -> Result<AxisBinding, TokenError> {
+    let text = text.trim();
+    if let Some((neg, pos)) = text.split_once(AXIS_SEPARATOR) {
+        return Ok(AxisBinding::Buttons(parse_button(neg)?, parse_button(pos)?));
+    }
+
+    let (device, name) = text.split_once(':').unwrap_or(("", text));
+    let axis = match device {
+        "Pad" => parse_variant(name).map(AxisBinding::GamepadAxis),
+        "TouchStickX" => parse_stick(name).map(AxisBinding::TouchStickX),
+        "TouchStickY" => parse_stick(name).map(AxisBinding::TouchStickY),
+        _ => {
+            return Err(TokenError::new(
+                text,
+                "expected a `Pad:`, `TouchStickX:` or `TouchStickY:` axis or a `negative/positive` button pair",
+            ))
+        }
+    };
+
+    axis.ok_or_else(|| TokenError::new(text, format!("unknown {} axis", device)))
+}
+
+fn format_region(region: TouchRegion) -> String {
+    format!(
+        "{},{},{},{}",
+        region.left, region.top, region.right, region.bottom
+    )
+}
+
+// `left,top,right,bottom` in window coordinates
+fn parse_region(text: &str) -> Option<TouchRegion> {
+    let values = text
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<Vec<i32>>>()?;
+
+    match values[..] {
+        [left, top, right, bottom] => Some(TouchRegion::new(left, top, right, bottom)),
+        _ => None,
+    }
+}
+
+fn format_stick(stick: TouchStick) -> String {
+    format!("{},{}", format_region(stick.region), stick.radius)
+}
+
+// `left,top,right,bottom,radius`
+fn parse_stick(text: &str) -> Option<TouchStick> {
+    let (region, radius) = text.rsplit_once(',')?;
+    Some(TouchStick::new(
+        parse_region(region)?,
+        radius.trim().parse().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_axis, format_binding, parse_axis, parse_binding, parse_button, TokenError};
+    use crate::{inputs_vec, AxisBinding, ButtonCode, TouchGesture, TouchRegion, TouchStick};
+    use bevy::prelude::*;
+    use test_case::test_case;
+
+    #[test_case(inputs_vec![KeyCode::Space] => "Space")]
+    #[test_case(inputs_vec![MouseButton::Left, KeyCode::LControl] => "Ctrl+Mouse:Left")]
+    #[test_case(inputs_vec![KeyCode::S, KeyCode::LAlt, KeyCode::LShift] => "Shift+Alt+S")]
+    #[test_case(inputs_vec![KeyCode::RControl] => "RControl")]
+    #[test_case(inputs_vec![GamepadButtonType::South] => "Pad:South")]
+    #[test_case(inputs_vec![MouseButton::Other(4)] => "Mouse:4")]
+    #[test_case(vec![ButtonCode::ScanCode(57)] => "Scan:57")]
+    #[test_case(inputs_vec![TouchRegion::new(0, 0, 100, 50)] => "Touch:0,0,100,50")]
+    #[test_case(inputs_vec![TouchGesture::SwipeLeft] => "Gesture:SwipeLeft")]
+    fn binding_round_trip(binding: Vec<ButtonCode>) -> String {
+        let binding = binding.into_iter().collect();
+        let text = format_binding(&binding);
+        assert_eq!(binding, parse_binding(&text).unwrap());
+        text
+    }
+
+    #[test_case(AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX) => "Pad:LeftStickX")]
+    #[test_case(AxisBinding::Buttons(KeyCode::A.into(), KeyCode::D.into()) => "A/D")]
+    #[test_case(AxisBinding::TouchStickY(TouchStick::new(TouchRegion::new(0, 0, 200, 200), 50)) => "TouchStickY:0,0,200,200,50")]
+    fn axis_round_trip(binding: AxisBinding) -> String {
+        let text = format_axis(&binding);
+        assert_eq!(binding, parse_axis(&text).unwrap());
+        text
+    }
+
+    #[test_case("Key:Space" => Ok(KeyCode::Space.into()))]
+    #[test_case(" Shift " => Ok(KeyCode::LShift.into()))]
+    #[test_case("Spcae" => Err(TokenError::new("Spcae", "unknown Key button")))]
+    #[test_case("Pad:Soth" => Err(TokenError::new("Pad:Soth", "unknown Pad button")))]
+    #[test_case("Joy:South" => Err(TokenError::new("Joy:South", "unknown device `Joy`")))]
+    fn button(token: &str) -> Result<ButtonCode, TokenError> {
+        parse_button(token)
+    }
+
+    #[test]
+    fn bad_token_in_combination() {
+        assert_eq!(
+            Err(TokenError::new("Mouse:Lft", "unknown Mouse button")),
+            parse_binding("Ctrl+Mouse:Lft")
+        );
+    }
+}
@@ -1,103 +1,793 @@
-use bevy::{ prelude::*, tasks::{AsyncComputeTaskPool, Task}};
-use ron::ser::PrettyConfig;
-use serde::{Deserialize, Serialize};
-use crate::{ActionMap, ActionMapInput};
+use crate::{
+    location::write_atomic, versioning::BindingsFile, ActionMap, BindingError, BindingsLocation,
+    BindingsMigrations, BindingsPaths, MapFormat, SerializableActionInput,
+};
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
 use futures_lite::future;
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-pub enum MapIoRequest {
-    Load(String),
-    Save(String),
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapIoRequestId(u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapIoKind {
+    Load,
+    Save,
+}
+
+// requests are processed one at a time in the order they were sent, by the registered action types
+// whose `ActionMap` is on the entity - requests for entities without one fail with `MissingMap`
+#[derive(Debug, Clone)]
+pub struct MapIoRequest {
+    id: MapIoRequestId,
+    kind: MapIoKind,
+    entity: Entity,
+    location: BindingsLocation,
+    format: MapFormat,
 }
 
+impl MapIoRequest {
+    pub fn load(entity: Entity, location: impl Into<BindingsLocation>) -> Self {
+        Self::new(MapIoKind::Load, entity, location.into())
+    }
+
+    // the map is read when the save starts, so queued saves of the same map and path are merged
+    pub fn save(entity: Entity, location: impl Into<BindingsLocation>) -> Self {
+        Self::new(MapIoKind::Save, entity, location.into())
+    }
+
+    fn new(kind: MapIoKind, entity: Entity, location: BindingsLocation) -> Self {
+        Self {
+            id: MapIoRequestId(NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)),
+            kind,
+            entity,
+            format: MapFormat::from_path(location.path()),
+            location,
+        }
+    }
+
+    // overrides the format inferred from the path extension
+    #[must_use]
+    pub fn with_format(mut self, format: MapFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn id(&self) -> MapIoRequestId {
+        self.id
+    }
+
+    pub fn kind(&self) -> MapIoKind {
+        self.kind
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn location(&self) -> &BindingsLocation {
+        &self.location
+    }
+
+    pub fn format(&self) -> MapFormat {
+        self.format
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum MapIoEvent {
-    Loaded,
-    Saved,
+    Loaded {
+        id: MapIoRequestId,
+        entity: Entity,
+        path: String,
+    },
+    Saved {
+        id: MapIoRequestId,
+        entity: Entity,
+        path: String,
+    },
+    LoadFailed {
+        id: MapIoRequestId,
+        entity: Entity,
+        path: String,
+        error: MapIoError,
+    },
+    SaveFailed {
+        id: MapIoRequestId,
+        entity: Entity,
+        path: String,
+        error: MapIoError,
+    },
 }
 
-pub(crate) struct ActionMapLoad<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(pub(crate) Option<Task<anyhow::Result<ActionMap<TKeyAction, TAxisAction>>>>);
+impl MapIoEvent {
+    pub fn id(&self) -> MapIoRequestId {
+        match self {
+            Self::Loaded { id, .. }
+            | Self::Saved { id, .. }
+            | Self::LoadFailed { id, .. }
+            | Self::SaveFailed { id, .. } => *id,
+        }
+    }
 
-pub(crate) struct ActionMapSave(pub(crate) Option<Task<anyhow::Result<()>>>);
+    pub fn entity(&self) -> Entity {
+        match self {
+            Self::Loaded { entity, .. }
+            | Self::Saved { entity, .. }
+            | Self::LoadFailed { entity, .. }
+            | Self::SaveFailed { entity, .. } => *entity,
+        }
+    }
+}
 
-pub(crate) fn process_map_event<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
-    mut event_r: EventReader<MapIoRequest>,
-    mut load: ResMut<ActionMapLoad<TKeyAction, TAxisAction>>,
-    mut save: ResMut<ActionMapSave>,
-    thread_pool: Res<AsyncComputeTaskPool>,
-    map: Res<ActionMap<TKeyAction, TAxisAction>>,
-)
-where
-    for<'de> TKeyAction: ActionMapInput + Serialize + Deserialize<'de> + 'static,
-    for<'de> TAxisAction: ActionMapInput + Serialize + Deserialize<'de> + 'static,
+#[derive(Debug, Clone)]
+pub enum MapIoError {
+    Io(String),
+    Parse {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    Serialize(String),
+    InvalidBindings(BindingError),
+    // the file was written by a newer version than the latest registered migration
+    UnsupportedVersion {
+        version: u32,
+        latest: u32,
+    },
+    UnknownAction(String),
+    // a binding of `action` contains a token that isn't a known button or axis
+    InvalidToken {
+        action: String,
+        token: String,
+        reason: String,
+    },
+    // the target entity has no `ActionMap` (anymore)
+    MissingMap,
+    // the platform has no user config directory to resolve `BindingsLocation::UserConfig` in
+    NoConfigDir,
+}
+
+impl Display for MapIoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) | Self::Serialize(err) => write!(f, "{}", err),
+            Self::Parse {
+                message,
+                line,
+                column,
+            } => write!(f, "{} at line {}, column {}", message, line, column),
+            Self::InvalidBindings(BindingError::Conflict(binding)) => {
+                write!(f, "conflicting binding {:?}", binding.value())
+            }
+            Self::UnsupportedVersion { version, latest } => write!(
+                f,
+                "bindings version {} is newer than the supported version {}",
+                version, latest
+            ),
+            Self::UnknownAction(action) => write!(f, "unknown action {}", action),
+            Self::InvalidToken {
+                action,
+                token,
+                reason,
+            } => write!(f, "invalid binding `{}` for {}: {}", token, action, reason),
+            Self::MissingMap => write!(f, "the entity has no action map"),
+            Self::NoConfigDir => write!(f, "no user config directory"),
+        }
+    }
+}
+
+impl std::error::Error for MapIoError {}
+
+impl From<std::io::Error> for MapIoError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string())
+    }
+}
+
+impl From<ron::Error> for MapIoError {
+    fn from(err: ron::Error) -> Self {
+        Self::Parse {
+            message: err.code.to_string(),
+            line: err.position.line,
+            column: err.position.col,
+        }
+    }
+}
+
+impl From<BindingError> for MapIoError {
+    fn from(err: BindingError) -> Self {
+        Self::InvalidBindings(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadFailurePolicy {
+    KeepCurrent,
+    // keeps the current bindings when there's no `DefaultActionMap`
+    UseDefaults,
+}
+
+#[derive(Debug, Clone)]
+pub struct MapIoSettings {
+    pub load_failure_policy: LoadFailurePolicy,
+    // skip unknown actions instead of failing the whole file
+    pub lenient: bool,
+    // keep the previous file as `<name>.bak` when saving
+    pub backup: bool,
+}
+
+impl Default for MapIoSettings {
+    fn default() -> Self {
+        Self {
+            load_failure_policy: LoadFailurePolicy::KeepCurrent,
+            lenient: false,
+            backup: true,
+        }
+    }
+}
+
+// saved bindings only store the differences to these and are merged with them on load,
+// also applied by `LoadFailurePolicy::UseDefaults`, saved files are merged with an empty map when
+// this resource is missing
+pub struct DefaultActionMap<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput,
+>(pub ActionMap<TKeyAction, TAxisAction>);
+
+struct QueuedRequest {
+    request: MapIoRequest,
+    // ids of the saves merged into this one
+    merged_ids: Vec<MapIoRequestId>,
+}
+
+enum IoTask<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> {
+    Load(Task<Result<ActionMap<TKeyAction, TAxisAction>, MapIoError>>),
+    Save(Task<Result<(), MapIoError>>),
+}
+
+struct InFlightRequest<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> {
+    request: QueuedRequest,
+    task: IoTask<TKeyAction, TAxisAction>,
+}
+
+pub(crate) struct MapIoQueue<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput,
+> {
+    queue: VecDeque<QueuedRequest>,
+    in_flight: Option<InFlightRequest<TKeyAction, TAxisAction>>,
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> Default
+    for MapIoQueue<TKeyAction, TAxisAction>
+{
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            in_flight: None,
+        }
+    }
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput>
+    MapIoQueue<TKeyAction, TAxisAction>
 {
-    // loading or saving already
-    if load.0.is_some() || save.0.is_some() {
-        return;
-    }
-
-    for ev in event_r.iter() {
-        match ev {
-            MapIoRequest::Load(path) => {
-                let path = path.clone();
-                let task: Task<anyhow::Result<_>> = thread_pool.spawn(async move {
-                    // todo: async file read
-                    let bytes = std::fs::read(path)?;
-                    let map = ron::de::from_bytes::<ActionMap<TKeyAction, TAxisAction>>(&bytes)?;
-                    Ok(map)
+    fn push(&mut self, request: MapIoRequest) {
+        if request.kind == MapIoKind::Save {
+            // merge into the last queued save of the same map and path unless the map is loaded or the file used in between
+            let merge_target = self
+                .queue
+                .iter_mut()
+                .rev()
+                .find(|queued| {
+                    (queued.request.kind == MapIoKind::Load
+                        && queued.request.entity == request.entity)
+                        || queued.request.location == request.location
+                })
+                .filter(|queued| {
+                    queued.request.kind == MapIoKind::Save
+                        && queued.request.entity == request.entity
+                        && queued.request.location == request.location
                 });
-            
-                load.0 = Some(task);
+
+            if let Some(queued) = merge_target {
+                queued.merged_ids.push(request.id);
                 return;
-            },
-            MapIoRequest::Save(path) => {
-                let map = map.clone();
-                let path = path.clone();
-                let task: Task<Result<_, anyhow::Error>> = thread_pool.spawn(async move {
-                    let map_str = ron::ser::to_string_pretty(&map, PrettyConfig::default())?;
-                    // todo: async file write
-                    std::fs::write(path, map_str)?;
-                    Ok(())
-                });
-            
-                save.0 = Some(task);
+            }
+        }
+
+        self.queue.push_back(QueuedRequest {
+            request,
+            merged_ids: Vec::new(),
+        });
+    }
+}
+
+// ids of the requests an action type pair found its map for, not generic so unclaimed requests can
+// be reported once for all of them
+#[derive(Default)]
+pub(crate) struct MapIoClaims(HashSet<MapIoRequestId>);
+
+pub(crate) fn process_map_io<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    mut event_r: EventReader<MapIoRequest>,
+    mut event_w: EventWriter<MapIoEvent>,
+    mut io_queue: ResMut<MapIoQueue<TKeyAction, TAxisAction>>,
+    mut claims: ResMut<MapIoClaims>,
+    mut map_q: Query<&mut ActionMap<TKeyAction, TAxisAction>>,
+    thread_pool: Res<AsyncComputeTaskPool>,
+    settings: Res<MapIoSettings>,
+    migrations: Res<BindingsMigrations>,
+    paths: Res<BindingsPaths>,
+    defaults: Option<Res<DefaultActionMap<TKeyAction, TAxisAction>>>,
+) {
+    // requests for maps of other action types
+    for request in event_r.iter().filter(|req| map_q.get(req.entity).is_ok()) {
+        claims.0.insert(request.id);
+        io_queue.push(request.clone());
+    }
+
+    loop {
+        if let Some(mut in_flight) = io_queue.in_flight.take() {
+            let finished = match &mut in_flight.task {
+                IoTask::Load(task) => future::block_on(future::poll_once(task)).map(|res| {
+                    let map = map_q.get_mut(in_flight.request.request.entity).ok();
+                    apply_loaded_map(res, map, &settings, defaults.as_deref())
+                }),
+                IoTask::Save(task) => future::block_on(future::poll_once(task)),
+            };
+
+            if let Some(res) = finished {
+                send_completion(&mut event_w, &in_flight.request, &paths, res);
+            } else {
+                io_queue.in_flight = Some(in_flight);
                 return;
-            },
+            }
+        }
+
+        let queued = if let Some(queued) = io_queue.queue.pop_front() {
+            queued
+        } else {
+            return;
+        };
+
+        let map = map_q.get(queued.request.entity).ok().cloned();
+        let started = paths.resolve(&queued.request.location).and_then(|path| {
+            start_request::<TKeyAction, TAxisAction>(
+                &queued.request,
+                path,
+                map,
+                &thread_pool,
+                defaults.as_deref(),
+                &migrations,
+                &settings,
+            )
+        });
+        match started {
+            Ok(task) => {
+                io_queue.in_flight = Some(InFlightRequest {
+                    request: queued,
+                    task,
+                });
+            }
+            Err(error) => send_completion(&mut event_w, &queued, &paths, Err(error)),
         }
     }
 }
 
-pub(crate) fn load_map<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
-    mut map: ResMut<ActionMap<TKeyAction, TAxisAction>>,
-    mut load: ResMut<ActionMapLoad<TKeyAction, TAxisAction>>,
+// runs after every `process_map_io`, requests sent later in the frame are only read by those in the
+// next frame, so unclaimed requests get one more frame before they're reported
+pub(crate) fn report_unclaimed_map_io(
+    mut event_r: EventReader<MapIoRequest>,
     mut event_w: EventWriter<MapIoEvent>,
-) -> anyhow::Result<()>
-where
-    for<'de> TKeyAction: ActionMapInput + 'static,
-    for<'de> TAxisAction: ActionMapInput + 'static
-{
-    if let Some(ref mut task) = load.0 {
-        if let Some(serialized_map_res) = future::block_on(future::poll_once(&mut *task)) {
-            let serialized_map = serialized_map_res?;
-            map.set_bindings(serialized_map.key_action_bindings,  serialized_map. axis_action_bindings);
-            load.0 = None;
-            event_w.send(MapIoEvent::Loaded);
+    mut claims: ResMut<MapIoClaims>,
+    paths: Res<BindingsPaths>,
+    mut unclaimed: Local<Vec<MapIoRequest>>,
+) {
+    for request in std::mem::take(&mut *unclaimed) {
+        if !claims.0.remove(&request.id) {
+            let queued = QueuedRequest {
+                request,
+                merged_ids: Vec::new(),
+            };
+            send_completion(&mut event_w, &queued, &paths, Err(MapIoError::MissingMap));
         }
     }
 
-    Ok(())
+    for request in event_r.iter() {
+        if !claims.0.remove(&request.id) {
+            unclaimed.push(request.clone());
+        }
+    }
 }
 
-pub(crate) fn save_map(
-    mut save: ResMut<ActionMapSave>,
-    mut event_w: EventWriter<MapIoEvent>,
-) -> anyhow::Result<()>
-{
-    if let Some(ref mut task) = save.0 {
-        if let Some(res) = future::block_on(future::poll_once(&mut *task)) {
-            res?;
-            save.0 = None;
-            event_w.send(MapIoEvent::Saved);
+fn start_request<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    request: &MapIoRequest,
+    path: PathBuf,
+    map: Option<ActionMap<TKeyAction, TAxisAction>>,
+    thread_pool: &AsyncComputeTaskPool,
+    defaults: Option<&DefaultActionMap<TKeyAction, TAxisAction>>,
+    migrations: &BindingsMigrations,
+    settings: &MapIoSettings,
+) -> Result<IoTask<TKeyAction, TAxisAction>, MapIoError> {
+    let format = request.format;
+    let (lenient, backup) = (settings.lenient, settings.backup);
+    let defaults = defaults.map_or_else(ActionMap::default, |d| d.0.clone());
+
+    match request.kind {
+        MapIoKind::Load => {
+            let migrations = migrations.clone();
+            Ok(IoTask::Load(thread_pool.spawn(async move {
+                let bytes = async_fs::read(path).await?;
+                format.deserialize::<BindingsFile>(&bytes)?.into_map(
+                    &defaults,
+                    &migrations,
+                    lenient,
+                )
+            })))
+        }
+        MapIoKind::Save => {
+            let map = map.ok_or(MapIoError::MissingMap)?;
+            let file = BindingsFile::from_map(&map, &defaults, migrations.version())?;
+            Ok(IoTask::Save(thread_pool.spawn(async move {
+                let bytes = format.serialize(&file)?;
+                write_atomic(path, bytes, backup).await
+            })))
+        }
+    }
+}
+
+fn apply_loaded_map<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput>(
+    res: Result<ActionMap<TKeyAction, TAxisAction>, MapIoError>,
+    map: Option<Mut<ActionMap<TKeyAction, TAxisAction>>>,
+    settings: &MapIoSettings,
+    defaults: Option<&DefaultActionMap<TKeyAction, TAxisAction>>,
+) -> Result<(), MapIoError> {
+    let mut map = map.ok_or(MapIoError::MissingMap)?;
+
+    let res = res.and_then(|loaded_map| {
+        map.try_set_bindings(
+            loaded_map.key_action_bindings,
+            loaded_map.axis_action_bindings,
+        )
+        .map_err(MapIoError::from)
+    });
+
+    if let (Err(_), LoadFailurePolicy::UseDefaults, Some(defaults)) =
+        (&res, settings.load_failure_policy, defaults)
+    {
+        let defaults = defaults.0.clone();
+        map.set_bindings(defaults.key_action_bindings, defaults.axis_action_bindings);
+    }
+
+    res
+}
+
+fn send_completion(
+    event_w: &mut EventWriter<MapIoEvent>,
+    queued: &QueuedRequest,
+    paths: &BindingsPaths,
+    res: Result<(), MapIoError>,
+) {
+    let MapIoRequest {
+        kind,
+        entity,
+        location,
+        ..
+    } = &queued.request;
+    // unresolved locations are reported by their relative path
+    let path: String = paths
+        .resolve(location)
+        .unwrap_or_else(|_| location.path().to_path_buf())
+        .to_string_lossy()
+        .into();
+
+    for id in std::iter::once(queued.request.id).chain(queued.merged_ids.iter().copied()) {
+        let (entity, path) = (*entity, path.clone());
+        event_w.send(match (kind, res.clone()) {
+            (MapIoKind::Load, Ok(())) => MapIoEvent::Loaded { id, entity, path },
+            (MapIoKind::Save, Ok(())) => MapIoEvent::Saved { id, entity, path },
+            (MapIoKind::Load, Err(error)) => MapIoEvent::LoadFailed {
+                id,
+                entity,
+                path,
+                error,
+            },
+            (MapIoKind::Save, Err(error)) => MapIoEvent::SaveFailed {
+                id,
+                entity,
+                path,
+                error,
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DefaultActionMap, LoadFailurePolicy, MapIoError, MapIoEvent, MapIoRequestId, MapIoSettings,
+    };
+    use crate::{
+        test_support::fixtures::{
+            spawn_player, test_app, test_map, MenuAction, TestAction, TestAxis, TestMap,
+        },
+        ActionMap, AppActionInputExt, MapIoRequest,
+    };
+    use bevy::{
+        ecs::event::{Events, ManualEventReader},
+        prelude::*,
+    };
+
+    fn setup_app(map: TestMap) -> (App, Entity) {
+        let mut app = test_app();
+        app.add_action_serialize_systems_with_axis::<TestAction, TestAxis>();
+        let e = spawn_player(&mut app, map);
+        app.update();
+        (app, e)
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("bevy_input_{}_{}", std::process::id(), name))
+            .to_string_lossy()
+            .into()
+    }
+
+    fn send(app: &mut App, req: MapIoRequest) -> MapIoRequestId {
+        let id = req.id();
+        app.world.resource_mut::<Events<MapIoRequest>>().send(req);
+        id
+    }
+
+    fn wait_for(app: &mut App, ids: &[MapIoRequestId]) -> Vec<MapIoEvent> {
+        let mut reader = ManualEventReader::<MapIoEvent>::default();
+        let mut received = Vec::new();
+
+        for _ in 0..200 {
+            app.update();
+            let events = app.world.resource::<Events<MapIoEvent>>();
+            received.extend(
+                reader
+                    .iter(events)
+                    .filter(|ev| ids.contains(&ev.id()))
+                    .cloned(),
+            );
+            if received.len() == ids.len() {
+                return received;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        panic!("map io requests timed out");
+    }
+
+    fn request(app: &mut App, req: MapIoRequest) -> MapIoEvent {
+        let id = send(app, req);
+        wait_for(app, &[id]).remove(0)
+    }
+
+    fn jump_map(key: KeyCode) -> TestMap {
+        test_map([(TestAction::Jump, key.into())], [])
+    }
+
+    fn is_bound(app: &App, e: Entity, action: TestAction) -> bool {
+        app.world
+            .get::<TestMap>(e)
+            .unwrap()
+            .get_key_bindings()
+            .contains_key(&action.into())
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temp_path("save_and_load.bindings");
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+
+        let ev = request(&mut app, MapIoRequest::save(e, path.clone()));
+        assert!(matches!(ev, MapIoEvent::Saved { .. }));
+
+        app.world.get_mut::<TestMap>(e).unwrap().clear_bindings();
+        let ev = request(&mut app, MapIoRequest::load(e, path.clone()));
+        assert!(matches!(ev, MapIoEvent::Loaded { .. }));
+        assert!(is_bound(&app, e, TestAction::Jump));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn malformed_file_falls_back_to_defaults() {
+        let path = temp_path("malformed.bindings");
+        std::fs::write(&path, "(\n    version: 1,\n    keys: {\n        oops\n").unwrap();
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+        let mut defaults = TestMap::default();
+        defaults
+            .bind_button_action(TestAction::Shoot, KeyCode::LControl)
+            .unwrap();
+        app.insert_resource(DefaultActionMap(defaults))
+            .insert_resource(MapIoSettings {
+                load_failure_policy: LoadFailurePolicy::UseDefaults,
+                ..Default::default()
+            });
+
+        let ev = request(&mut app, MapIoRequest::load(e, path.clone()));
+        if let MapIoEvent::LoadFailed {
+            error: MapIoError::Parse { line, .. },
+            ..
+        } = ev
+        {
+            assert_eq!(4, line);
+        } else {
+            panic!("unexpected event {:?}", ev);
+        }
+        assert!(!is_bound(&app, e, TestAction::Jump));
+        assert!(is_bound(&app, e, TestAction::Shoot));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_file_keeps_current() {
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+
+        let ev = request(
+            &mut app,
+            MapIoRequest::load(e, temp_path("missing.bindings")),
+        );
+        assert!(matches!(
+            ev,
+            MapIoEvent::LoadFailed {
+                error: MapIoError::Io(_),
+                ..
+            }
+        ));
+        assert!(is_bound(&app, e, TestAction::Jump));
+
+        // no defaults to fall back to
+        app.insert_resource(MapIoSettings {
+            load_failure_policy: LoadFailurePolicy::UseDefaults,
+            ..Default::default()
+        });
+        let ev = request(
+            &mut app,
+            MapIoRequest::load(e, temp_path("missing.bindings")),
+        );
+        assert!(matches!(ev, MapIoEvent::LoadFailed { .. }));
+        assert!(is_bound(&app, e, TestAction::Jump));
+    }
+
+    #[test]
+    fn other_action_types_ignore_requests() {
+        let path = temp_path("other_action_types.bindings");
+        let menu_path = temp_path("other_action_types_menu.bindings");
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+        let mut menu_map = ActionMap::<MenuAction>::default();
+        menu_map
+            .bind_button_action(MenuAction::Confirm, KeyCode::Return)
+            .unwrap();
+        app.add_action_input_systems::<MenuAction>()
+            .add_action_serialize_systems::<MenuAction>();
+        let menu_e = app.world.spawn().insert(menu_map).id();
+
+        let save_id = send(&mut app, MapIoRequest::save(e, path.clone()));
+        let menu_save_id = send(&mut app, MapIoRequest::save(menu_e, menu_path.clone()));
+        let mut reader = ManualEventReader::<MapIoEvent>::default();
+        let mut events = Vec::new();
+        for _ in 0..200 {
+            app.update();
+            events.extend(
+                reader
+                    .iter(app.world.resource::<Events<MapIoEvent>>())
+                    .cloned(),
+            );
+            if events.len() >= 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
         }
+
+        // no failures from the action types without a map on the entity,
+        // each type pair has its own queue so they complete in any order
+        let mut completed: Vec<_> = events.iter().map(|ev| (ev.id(), ev.entity())).collect();
+        completed.sort_by_key(|(id, _)| id.0);
+        assert_eq!(vec![(save_id, e), (menu_save_id, menu_e)], completed);
+        assert!(events
+            .iter()
+            .all(|ev| matches!(ev, MapIoEvent::Saved { .. })));
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(menu_path).unwrap();
+    }
+
+    #[test]
+    fn queued_requests_complete_in_order() {
+        let path = temp_path("queued.bindings");
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+
+        let save_id = send(&mut app, MapIoRequest::save(e, path.clone()));
+        app.world.get_mut::<TestMap>(e).unwrap().clear_bindings();
+        let load_id = send(&mut app, MapIoRequest::load(e, path.clone()));
+
+        let events = wait_for(&mut app, &[save_id, load_id]);
+        assert!(matches!(events[0], MapIoEvent::Saved { id, .. } if id == save_id));
+        assert!(matches!(events[1], MapIoEvent::Loaded { id, .. } if id == load_id));
+        // the save read the map after it was cleared
+        assert!(!is_bound(&app, e, TestAction::Jump));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn repeated_saves_are_merged() {
+        let path = temp_path("merged.bindings");
+        let other_path = temp_path("merged_other.bindings");
+        let (mut app, e) = setup_app(jump_map(KeyCode::Space));
+
+        let first_id = send(&mut app, MapIoRequest::save(e, path.clone()));
+        let other_id = send(&mut app, MapIoRequest::save(e, other_path.clone()));
+        app.world
+            .get_mut::<TestMap>(e)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::LControl)
+            .unwrap();
+        let second_id = send(&mut app, MapIoRequest::save(e, path.clone()));
+
+        let events = wait_for(&mut app, &[first_id, other_id, second_id]);
+        assert_eq!(
+            vec![first_id, second_id, other_id],
+            events.iter().map(MapIoEvent::id).collect::<Vec<_>>()
+        );
+        assert!(events
+            .iter()
+            .all(|ev| matches!(ev, MapIoEvent::Saved { .. })));
+        assert!(std::fs::read_to_string(&path).unwrap().contains("Shoot"));
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(other_path).unwrap();
     }
 
-    Ok(())
+    #[test]
+    fn requests_without_a_map_fail() {
+        let (mut app, _) = setup_app(jump_map(KeyCode::Space));
+        let e = app.world.spawn().id();
+
+        let ev = request(
+            &mut app,
+            MapIoRequest::load(e, temp_path("no_map.bindings")),
+        );
+        assert!(matches!(
+            ev,
+            MapIoEvent::LoadFailed {
+                error: MapIoError::MissingMap,
+                ..
+            }
+        ));
+        let ev = request(
+            &mut app,
+            MapIoRequest::save(e, temp_path("no_map.bindings")),
+        );
+        assert!(matches!(
+            ev,
+            MapIoEvent::SaveFailed {
+                error: MapIoError::MissingMap,
+                ..
+            }
+        ));
+    }
 }
@@ -0,0 +1,300 @@
+use crate::{
+    action_map::{ButtonState, PlayerData},
+    device::InputDevice,
+    input_time::{InputTime, InputTimestamp},
+    ActionInput, ActionMap, ActionMapInput, ButtonCode, InputGamepad,
+};
+use bevy::{
+    input::{
+        gamepad::{GamepadEvent, GamepadEventType, GamepadSettings},
+        keyboard::KeyboardInput,
+        mouse::MouseButtonInput,
+        ElementState,
+    },
+    prelude::*,
+};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Default)]
+pub struct ButtonStateSettings {
+    // derive keyboard, mouse and gamepad button states from the device events in the order they
+    // arrived instead of polling `Input`, a press and release within one frame is then reported
+    // over two frames rather than merged, and is timestamped with the position of its event
+    pub event_driven: bool,
+}
+
+// gamepad buttons are tracked per gamepad
+type TrackedButton = (ButtonCode, Option<usize>);
+
+// transitions that are still to be reported, one per frame
+#[derive(Debug, Default)]
+struct ButtonQueue {
+    down: bool,
+    pending: VecDeque<(bool, InputTimestamp)>,
+}
+
+impl ButtonQueue {
+    fn is_down(&self) -> bool {
+        self.pending.back().map_or(self.down, |(down, _)| *down)
+    }
+
+    fn push(&mut self, down: bool, timestamp: InputTimestamp) {
+        // repeated presses of a held key are ignored
+        if self.is_down() != down {
+            self.pending.push_back((down, timestamp));
+        }
+    }
+
+    fn next_state(&mut self) -> Option<(ButtonState, Option<InputTimestamp>)> {
+        match self.pending.pop_front() {
+            Some((down, timestamp)) => {
+                self.down = down;
+                let state = if down {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Released
+                };
+                Some((state, Some(timestamp)))
+            }
+            None if self.down => Some((ButtonState::Held, None)),
+            None => None,
+        }
+    }
+}
+
+// the state of a button this frame, along with when it changed
+type FrameState = (ButtonState, Option<InputTimestamp>);
+
+#[derive(Default)]
+pub(crate) struct ButtonEventStates {
+    queues: HashMap<TrackedButton, ButtonQueue>,
+    frame_states: HashMap<TrackedButton, FrameState>,
+}
+
+impl ButtonEventStates {
+    fn advance(&mut self) {
+        self.frame_states.clear();
+        for (button, queue) in &mut self.queues {
+            if let Some(state) = queue.next_state() {
+                self.frame_states.insert(*button, state);
+            }
+        }
+
+        self.queues
+            .retain(|_, queue| queue.down || !queue.pending.is_empty());
+    }
+
+    fn state(&self, button: ButtonCode, pad_id: Option<usize>) -> Option<FrameState> {
+        if let (ButtonCode::Gamepad(_), None) = (button, pad_id) {
+            // any gamepad, a transition wins over a held button
+            return self
+                .frame_states
+                .iter()
+                .filter(|((b, _), _)| *b == button)
+                .map(|(_, state)| *state)
+                .max_by_key(|(state, _)| *state != ButtonState::Held);
+        }
+
+        self.frame_states.get(&(button, pad_id)).copied()
+    }
+}
+
+pub(crate) fn handle_button_events<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(
+        &ActionMap<TKeyAction, TAxisAction>,
+        &mut ActionInput<TKeyAction, TAxisAction>,
+        Option<&InputGamepad>,
+    )>,
+    mut kb_events: EventReader<KeyboardInput>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    gamepad_settings: Res<GamepadSettings>,
+    settings: Res<ButtonStateSettings>,
+    time: Res<InputTime>,
+    mut states: Local<ButtonEventStates>,
+) {
+    if !settings.event_driven {
+        // keep the readers up to date for when the mode is switched on
+        kb_events.iter().last();
+        mouse_events.iter().last();
+        gamepad_events.iter().last();
+        return;
+    }
+
+    // events are numbered in the order they're read, across all devices
+    let mut ordinal = 0;
+    let mut next_timestamp = || {
+        ordinal += 1;
+        InputTimestamp::new(time.seconds_since_startup(), ordinal - 1)
+    };
+
+    for ev in kb_events.iter() {
+        let down = ev.state == ElementState::Pressed;
+        let timestamp = next_timestamp();
+        if let Some(key) = ev.key_code {
+            states
+                .queues
+                .entry((ButtonCode::Kb(key), None))
+                .or_default()
+                .push(down, timestamp);
+        }
+        states
+            .queues
+            .entry((ButtonCode::ScanCode(ev.scan_code), None))
+            .or_default()
+            .push(down, timestamp);
+    }
+
+    for ev in mouse_events.iter() {
+        states
+            .queues
+            .entry((ButtonCode::Mouse(ev.button), None))
+            .or_default()
+            .push(ev.state == ElementState::Pressed, next_timestamp());
+    }
+
+    for GamepadEvent(gamepad, event) in gamepad_events.iter() {
+        if let GamepadEventType::ButtonChanged(button, value) = event {
+            let button_settings =
+                gamepad_settings.get_button_settings(GamepadButton(*gamepad, *button));
+            let timestamp = next_timestamp();
+            let queue = states
+                .queues
+                .entry((ButtonCode::Gamepad(*button), Some(gamepad.0)))
+                .or_default();
+            // the same thresholds as `Input<GamepadButton>`
+            if queue.is_down() {
+                queue.push(*value > button_settings.release, timestamp);
+            } else {
+                queue.push(*value >= button_settings.press, timestamp);
+            }
+        }
+    }
+
+    states.advance();
+
+    for (map, mut input, pad) in input_q.iter_mut() {
+        for btn_data in &map.bound_keys {
+            let PlayerData { value: button, .. } = *btn_data;
+            let (state, device) = match button {
+                ButtonCode::Kb(_) | ButtonCode::ScanCode(_) => {
+                    (states.state(button, None), InputDevice::Keyboard)
+                }
+                ButtonCode::Mouse(_) => (states.state(button, None), InputDevice::Mouse),
+                ButtonCode::Gamepad(_) => {
+                    let pad_id = pad.map(|pad| pad.pad_id);
+                    let state = states.state(button, pad_id);
+                    // the pad the transition came from
+                    let pad_id = pad_id.or_else(|| {
+                        states
+                            .frame_states
+                            .keys()
+                            .find(|(b, _)| *b == button)
+                            .and_then(|(_, id)| *id)
+                    });
+                    (state, InputDevice::Gamepad(pad_id.unwrap_or_default()))
+                }
+                ButtonCode::Touch(_) | ButtonCode::Gesture(_) => continue,
+            };
+
+            let (state, timestamp) =
+                state.map_or((None, None), |(state, timestamp)| (Some(state), timestamp));
+            if state == Some(ButtonState::Pressed) {
+                input.last_device = Some(device);
+            }
+
+            input.button_states.insert(*btn_data, state);
+            if let Some(timestamp) = timestamp {
+                input.button_timestamps.insert(*btn_data, timestamp);
+            } else {
+                input.button_timestamps.remove(btn_data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ButtonStateSettings;
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction},
+        InputSimulationExt,
+    };
+    use bevy::prelude::*;
+    use pretty_assertions::assert_eq;
+
+    fn setup_app() -> (App, Entity) {
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Space.into()),
+                (TestAction::Shoot, MouseButton::Left.into()),
+                (TestAction::Crouch, GamepadButtonType::South.into()),
+            ],
+            [],
+        );
+
+        let mut app = test_app();
+        app.insert_resource(ButtonStateSettings { event_driven: true });
+        let e = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.1);
+        (app, e)
+    }
+
+    fn states(app: &mut App, e: Entity, action: TestAction, frames: usize) -> Vec<String> {
+        (0..frames)
+            .map(|_| {
+                app.advance_frames(1, 0.1);
+                input(app, e)
+                    .get_button_action_state(action)
+                    .map_or("-".to_string(), |state| format!("{:?}", state))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn taps_within_a_frame() {
+        let (mut app, e) = setup_app();
+
+        app.simulate_press(KeyCode::Space)
+            .simulate_release(KeyCode::Space);
+        assert_eq!(
+            vec!["Pressed", "Released: 0.10", "-"],
+            states(&mut app, e, TestAction::Jump, 3)
+        );
+
+        app.simulate_press(MouseButton::Left)
+            .simulate_release(MouseButton::Left);
+        assert_eq!(
+            vec!["Pressed", "Released: 0.10", "-"],
+            states(&mut app, e, TestAction::Shoot, 3)
+        );
+
+        app.simulate_press(GamepadButtonType::South)
+            .simulate_release(GamepadButtonType::South);
+        assert_eq!(
+            vec!["Pressed", "Released: 0.10", "-"],
+            states(&mut app, e, TestAction::Crouch, 3)
+        );
+    }
+
+    #[test]
+    fn retap_while_held() {
+        let (mut app, e) = setup_app();
+
+        app.simulate_press(KeyCode::Space);
+        assert_eq!(
+            vec!["Pressed", "Pressed: 0.10"],
+            states(&mut app, e, TestAction::Jump, 2)
+        );
+
+        app.simulate_release(KeyCode::Space)
+            .simulate_press(KeyCode::Space);
+        assert_eq!(
+            vec!["Released: 0.20", "Pressed", "Pressed: 0.10"],
+            states(&mut app, e, TestAction::Jump, 3)
+        );
+    }
+}
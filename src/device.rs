@@ -0,0 +1,151 @@
+use crate::{ActionInput, ActionMapInput};
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputDevice {
+    Keyboard,
+    Mouse,
+    // gamepad id
+    Gamepad(usize),
+    Touch,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveDeviceSettings {
+    // axis values (gamepad axes, analog buttons, touch sticks) at or below this are treated as noise
+    pub axis_noise_threshold: f32,
+    // cursor movement in logical pixels per frame at or below this doesn't switch to the mouse
+    pub cursor_noise_threshold: f32,
+}
+
+impl Default for ActiveDeviceSettings {
+    fn default() -> Self {
+        Self {
+            axis_noise_threshold: 0.2,
+            cursor_noise_threshold: 2.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveDeviceChanged {
+    pub entity: Entity,
+    pub device: InputDevice,
+    pub previous_device: Option<InputDevice>,
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionInput<TKeyAction, TAxisAction> {
+    pub fn last_device(&self) -> Option<InputDevice> {
+        self.last_device
+    }
+}
+
+pub(crate) fn emit_active_device_changes<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(Entity, &mut ActionInput<TKeyAction, TAxisAction>)>,
+    mut event_w: EventWriter<ActiveDeviceChanged>,
+) {
+    for (entity, mut input) in input_q.iter_mut() {
+        if input.last_device == input.reported_device {
+            continue;
+        }
+
+        if let Some(device) = input.last_device {
+            event_w.send(ActiveDeviceChanged {
+                entity,
+                device,
+                previous_device: input.reported_device,
+            });
+        }
+
+        input.reported_device = input.last_device;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActiveDeviceChanged, ActiveDeviceSettings, InputDevice};
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestAxis},
+        AxisBinding, InputGamepad,
+    };
+    use bevy::{
+        ecs::event::{Events, ManualEventReader},
+        input::{
+            gamepad::{GamepadEvent, GamepadEventType},
+            keyboard::KeyboardInput,
+            ElementState,
+        },
+        prelude::*,
+    };
+
+    fn send_axis(app: &mut App, pad_id: usize, strength: f32) {
+        app.world
+            .resource_mut::<Events<GamepadEvent>>()
+            .send(GamepadEvent(
+                Gamepad(pad_id),
+                GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, strength),
+            ));
+    }
+
+    fn device_changes(
+        app: &App,
+        reader: &mut ManualEventReader<ActiveDeviceChanged>,
+    ) -> Vec<InputDevice> {
+        let events = app.world.resource::<Events<ActiveDeviceChanged>>();
+        reader.iter(events).map(|ev| ev.device).collect()
+    }
+
+    #[test]
+    fn last_device() {
+        let mut app = test_app();
+        let map = test_map(
+            [(TestAction::Jump, KeyCode::Space.into())],
+            [(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )],
+        );
+        let e = spawn_player(&mut app, map);
+        app.world.entity_mut(e).insert(InputGamepad { pad_id: 1 });
+        app.update();
+        let mut reader = ManualEventReader::<ActiveDeviceChanged>::default();
+
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::Space),
+                state: ElementState::Pressed,
+            });
+        app.update();
+        assert_eq!(Some(InputDevice::Keyboard), input(&app, e).last_device());
+        assert_eq!(
+            vec![InputDevice::Keyboard],
+            device_changes(&app, &mut reader)
+        );
+
+        // below the noise threshold
+        send_axis(&mut app, 1, 0.1);
+        app.update();
+        // other pad
+        send_axis(&mut app, 0, 1.);
+        app.update();
+        assert_eq!(Some(InputDevice::Keyboard), input(&app, e).last_device());
+        assert!(device_changes(&app, &mut reader).is_empty());
+
+        app.world
+            .resource_mut::<ActiveDeviceSettings>()
+            .axis_noise_threshold = 0.05;
+        send_axis(&mut app, 1, 0.1);
+        app.update();
+        assert_eq!(Some(InputDevice::Gamepad(1)), input(&app, e).last_device());
+        assert_eq!(
+            vec![InputDevice::Gamepad(1)],
+            device_changes(&app, &mut reader)
+        );
+    }
+}
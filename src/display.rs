@@ -0,0 +1,609 @@
+use crate::{
+    action_map::KeyActionBinding, ActionMap, ActionMapInput, AxisBinding, ButtonCode, InputDevice,
+    TouchGesture,
+};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadFamily {
+    Generic,
+    Xbox,
+    PlayStation,
+    Nintendo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    KeyboardMouse,
+    Gamepad,
+    Touch,
+}
+
+impl DeviceKind {
+    pub fn of_button(button: ButtonCode) -> Self {
+        match button {
+            ButtonCode::Kb(_) | ButtonCode::ScanCode(_) | ButtonCode::Mouse(_) => {
+                Self::KeyboardMouse
+            }
+            ButtonCode::Gamepad(_) => Self::Gamepad,
+            ButtonCode::Touch(_) | ButtonCode::Gesture(_) => Self::Touch,
+        }
+    }
+
+    pub fn of_axis_binding(binding: &AxisBinding) -> Self {
+        match binding {
+            // both buttons of an axis binding are expected to come from the same device
+            AxisBinding::Buttons(neg, _) => Self::of_button(*neg),
+            AxisBinding::GamepadAxis(_) => Self::Gamepad,
+            AxisBinding::TouchStickX(_) | AxisBinding::TouchStickY(_) => Self::Touch,
+        }
+    }
+}
+
+impl From<InputDevice> for DeviceKind {
+    fn from(device: InputDevice) -> Self {
+        match device {
+            InputDevice::Keyboard | InputDevice::Mouse => Self::KeyboardMouse,
+            InputDevice::Gamepad(_) => Self::Gamepad,
+            InputDevice::Touch => Self::Touch,
+        }
+    }
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionMap<TKeyAction, TAxisAction> {
+    // first bound combination using only the given device kind
+    pub fn prompt_for(&self, action: TKeyAction, device: DeviceKind) -> Option<&KeyActionBinding> {
+        self.key_action_bindings
+            .get(&action.into())?
+            .iter()
+            .find(|binding| binding.iter().all(|b| DeviceKind::of_button(*b) == device))
+    }
+
+    // axis bindings aren't ordered, the same one is picked every time regardless of hashing
+    pub fn axis_prompt_for(&self, action: TAxisAction, device: DeviceKind) -> Option<&AxisBinding> {
+        self.axis_action_bindings
+            .get(&action.into())?
+            .iter()
+            .map(|(binding, _deadzone)| binding)
+            .filter(|binding| DeviceKind::of_axis_binding(binding) == device)
+            .min_by_key(|binding| (axis_binding_order(binding), format!("{:?}", binding)))
+    }
+}
+
+// player-facing names of bindings
+// the english defaults can be replaced (e.g. for localization) by the `with_*` overrides
+#[derive(Debug, Clone)]
+pub struct BindingNames {
+    button_names: HashMap<ButtonCode, String>,
+    gamepad_button_names: HashMap<(GamepadFamily, GamepadButtonType), String>,
+    axis_names: HashMap<GamepadAxisType, String>,
+    separator: String,
+    axis_separator: String,
+}
+
+impl Default for BindingNames {
+    fn default() -> Self {
+        Self {
+            button_names: Default::default(),
+            gamepad_button_names: Default::default(),
+            axis_names: Default::default(),
+            separator: " + ".into(),
+            axis_separator: " / ".into(),
+        }
+    }
+}
+
+impl BindingNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // overrides the name for all gamepad families
+    #[must_use]
+    pub fn with_button_name<B: Into<ButtonCode>, S: Into<String>>(
+        mut self,
+        button: B,
+        name: S,
+    ) -> Self {
+        self.button_names.insert(button.into(), name.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_gamepad_button_name<S: Into<String>>(
+        mut self,
+        family: GamepadFamily,
+        button: GamepadButtonType,
+        name: S,
+    ) -> Self {
+        self.gamepad_button_names
+            .insert((family, button), name.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_axis_name<S: Into<String>>(mut self, axis: GamepadAxisType, name: S) -> Self {
+        self.axis_names.insert(axis, name.into());
+        self
+    }
+
+    // joins buttons of a combination, " + " by default
+    #[must_use]
+    pub fn with_separator<S: Into<String>>(mut self, separator: S) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    // joins the negative and positive button of an axis, " / " by default
+    #[must_use]
+    pub fn with_axis_separator<S: Into<String>>(mut self, separator: S) -> Self {
+        self.axis_separator = separator.into();
+        self
+    }
+
+    pub fn button_name(&self, button: ButtonCode, family: GamepadFamily) -> String {
+        if let ButtonCode::Gamepad(gamepad_button) = button {
+            if let Some(name) = self.gamepad_button_names.get(&(family, gamepad_button)) {
+                return name.clone();
+            }
+        }
+
+        if let Some(name) = self.button_names.get(&button) {
+            return name.clone();
+        }
+
+        match button {
+            ButtonCode::Kb(key) => key_name(key),
+            ButtonCode::ScanCode(scan_code) => {
+                scan_code_key(scan_code).map_or_else(|| "Unknown Key".into(), key_name)
+            }
+            ButtonCode::Mouse(mouse_button) => mouse_button_name(mouse_button),
+            ButtonCode::Gamepad(gamepad_button) => {
+                gamepad_button_name(gamepad_button, family).into()
+            }
+            ButtonCode::Touch(_) => "Touch".into(),
+            ButtonCode::Gesture(gesture) => gesture_name(gesture).into(),
+        }
+    }
+
+    // modifiers go first, e.g. "Ctrl + Left Click"
+    pub fn binding_name(&self, binding: &KeyActionBinding, family: GamepadFamily) -> String {
+        let mut buttons: Vec<_> = binding
+            .iter()
+            .map(|b| (button_order(*b), self.button_name(*b, family)))
+            .collect();
+        buttons.sort();
+        buttons
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+
+    pub fn axis_name(&self, axis: GamepadAxisType) -> String {
+        if let Some(name) = self.axis_names.get(&axis) {
+            return name.clone();
+        }
+
+        match axis {
+            GamepadAxisType::LeftStickX => "Left Stick X",
+            GamepadAxisType::LeftStickY => "Left Stick Y",
+            GamepadAxisType::LeftZ => "Left Z",
+            GamepadAxisType::RightStickX => "Right Stick X",
+            GamepadAxisType::RightStickY => "Right Stick Y",
+            GamepadAxisType::RightZ => "Right Z",
+            GamepadAxisType::DPadX => "D-Pad X",
+            GamepadAxisType::DPadY => "D-Pad Y",
+        }
+        .into()
+    }
+
+    pub fn axis_binding_name(&self, binding: &AxisBinding, family: GamepadFamily) -> String {
+        match binding {
+            AxisBinding::Buttons(neg, pos) => format!(
+                "{}{}{}",
+                self.button_name(*neg, family),
+                self.axis_separator,
+                self.button_name(*pos, family)
+            ),
+            AxisBinding::GamepadAxis(axis) => self.axis_name(*axis),
+            AxisBinding::TouchStickX(_) => "Touch Stick X".into(),
+            AxisBinding::TouchStickY(_) => "Touch Stick Y".into(),
+        }
+    }
+
+    pub fn prompt_for<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
+        &self,
+        map: &ActionMap<TKeyAction, TAxisAction>,
+        action: TKeyAction,
+        device: DeviceKind,
+        family: GamepadFamily,
+    ) -> Option<String> {
+        map.prompt_for(action, device)
+            .map(|binding| self.binding_name(binding, family))
+    }
+
+    pub fn axis_prompt_for<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
+        &self,
+        map: &ActionMap<TKeyAction, TAxisAction>,
+        action: TAxisAction,
+        device: DeviceKind,
+        family: GamepadFamily,
+    ) -> Option<String> {
+        map.axis_prompt_for(action, device)
+            .map(|binding| self.axis_binding_name(binding, family))
+    }
+}
+
+fn button_order(button: ButtonCode) -> u8 {
+    match button {
+        ButtonCode::Kb(
+            KeyCode::LControl | KeyCode::RControl | KeyCode::LShift | KeyCode::RShift,
+        ) => 0,
+        ButtonCode::Kb(KeyCode::LAlt | KeyCode::RAlt | KeyCode::LWin | KeyCode::RWin) => 1,
+        ButtonCode::Kb(_) | ButtonCode::ScanCode(_) => 2,
+        ButtonCode::Mouse(_) => 3,
+        ButtonCode::Gamepad(_) => 4,
+        ButtonCode::Touch(_) | ButtonCode::Gesture(_) => 5,
+    }
+}
+
+// analog axes are shown before buttons
+fn axis_binding_order(binding: &AxisBinding) -> u8 {
+    match binding {
+        AxisBinding::GamepadAxis(_) | AxisBinding::TouchStickX(_) | AxisBinding::TouchStickY(_) => {
+            0
+        }
+        AxisBinding::Buttons(_, _) => 1,
+    }
+}
+
+// the key at the scan code position on a US layout, scan codes differ on macos
+#[cfg(not(target_os = "macos"))]
+fn scan_code_key(scan_code: u32) -> Option<KeyCode> {
+    const ROWS: [(u32, &[KeyCode]); 4] = [
+        (
+            2,
+            &[
+                KeyCode::Key1,
+                KeyCode::Key2,
+                KeyCode::Key3,
+                KeyCode::Key4,
+                KeyCode::Key5,
+                KeyCode::Key6,
+                KeyCode::Key7,
+                KeyCode::Key8,
+                KeyCode::Key9,
+                KeyCode::Key0,
+                KeyCode::Minus,
+                KeyCode::Equals,
+                KeyCode::Back,
+                KeyCode::Tab,
+            ],
+        ),
+        (
+            16,
+            &[
+                KeyCode::Q,
+                KeyCode::W,
+                KeyCode::E,
+                KeyCode::R,
+                KeyCode::T,
+                KeyCode::Y,
+                KeyCode::U,
+                KeyCode::I,
+                KeyCode::O,
+                KeyCode::P,
+                KeyCode::LBracket,
+                KeyCode::RBracket,
+                KeyCode::Return,
+                KeyCode::LControl,
+            ],
+        ),
+        (
+            30,
+            &[
+                KeyCode::A,
+                KeyCode::S,
+                KeyCode::D,
+                KeyCode::F,
+                KeyCode::G,
+                KeyCode::H,
+                KeyCode::J,
+                KeyCode::K,
+                KeyCode::L,
+                KeyCode::Semicolon,
+                KeyCode::Apostrophe,
+                KeyCode::Grave,
+                KeyCode::LShift,
+                KeyCode::Backslash,
+            ],
+        ),
+        (
+            44,
+            &[
+                KeyCode::Z,
+                KeyCode::X,
+                KeyCode::C,
+                KeyCode::V,
+                KeyCode::B,
+                KeyCode::N,
+                KeyCode::M,
+                KeyCode::Comma,
+                KeyCode::Period,
+                KeyCode::Slash,
+                KeyCode::RShift,
+            ],
+        ),
+    ];
+
+    match scan_code {
+        1 => Some(KeyCode::Escape),
+        56 => Some(KeyCode::LAlt),
+        57 => Some(KeyCode::Space),
+        _ => ROWS
+            .iter()
+            .find_map(|(first, keys)| keys.get(scan_code.checked_sub(*first)? as usize).copied()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn scan_code_key(_scan_code: u32) -> Option<KeyCode> {
+    None
+}
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Key1 => "1",
+        KeyCode::Key2 => "2",
+        KeyCode::Key3 => "3",
+        KeyCode::Key4 => "4",
+        KeyCode::Key5 => "5",
+        KeyCode::Key6 => "6",
+        KeyCode::Key7 => "7",
+        KeyCode::Key8 => "8",
+        KeyCode::Key9 => "9",
+        KeyCode::Key0 => "0",
+        KeyCode::Escape => "Esc",
+        KeyCode::Back => "Backspace",
+        KeyCode::Return | KeyCode::NumpadEnter => "Enter",
+        KeyCode::Left => "Left Arrow",
+        KeyCode::Right => "Right Arrow",
+        KeyCode::Up => "Up Arrow",
+        KeyCode::Down => "Down Arrow",
+        KeyCode::LControl | KeyCode::RControl => "Ctrl",
+        KeyCode::LShift | KeyCode::RShift => "Shift",
+        KeyCode::LAlt | KeyCode::RAlt => "Alt",
+        KeyCode::LWin | KeyCode::RWin => "Super",
+        KeyCode::PageUp => "Page Up",
+        KeyCode::PageDown => "Page Down",
+        _ => return format!("{:?}", key),
+    }
+    .into()
+}
+
+fn mouse_button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "Left Click".into(),
+        MouseButton::Right => "Right Click".into(),
+        MouseButton::Middle => "Middle Click".into(),
+        MouseButton::Other(button) => format!("Mouse {}", button),
+    }
+}
+
+fn gamepad_button_name(button: GamepadButtonType, family: GamepadFamily) -> &'static str {
+    use GamepadButtonType as B;
+    use GamepadFamily as F;
+
+    match (family, button) {
+        (F::Xbox, B::South) | (F::Nintendo, B::East) => "A",
+        (F::Xbox, B::East) | (F::Nintendo, B::South) => "B",
+        (F::Xbox, B::West) | (F::Nintendo, B::North) => "X",
+        (F::Xbox, B::North) | (F::Nintendo, B::West) => "Y",
+        (F::PlayStation, B::South) => "Cross",
+        (F::PlayStation, B::East) => "Circle",
+        (F::PlayStation, B::West) => "Square",
+        (F::PlayStation, B::North) => "Triangle",
+        (F::Xbox, B::LeftTrigger) => "LB",
+        (F::Xbox, B::RightTrigger) => "RB",
+        (F::Xbox, B::LeftTrigger2) => "LT",
+        (F::Xbox, B::RightTrigger2) => "RT",
+        (F::PlayStation, B::LeftTrigger) => "L1",
+        (F::PlayStation, B::RightTrigger) => "R1",
+        (F::PlayStation, B::LeftTrigger2) => "L2",
+        (F::PlayStation, B::RightTrigger2) => "R2",
+        (F::Nintendo, B::LeftTrigger) => "L",
+        (F::Nintendo, B::RightTrigger) => "R",
+        (F::Nintendo, B::LeftTrigger2) => "ZL",
+        (F::Nintendo, B::RightTrigger2) => "ZR",
+        (F::Xbox, B::Select) => "View",
+        (F::Xbox, B::Start) => "Menu",
+        (F::PlayStation, B::Select) => "Share",
+        (F::PlayStation, B::Start) => "Options",
+        (F::Nintendo, B::Select) => "-",
+        (F::Nintendo, B::Start) => "+",
+        (F::Xbox, B::Mode) => "Xbox",
+        (F::PlayStation, B::Mode) => "PS",
+        (F::Nintendo, B::Mode) => "Home",
+        (F::Xbox, B::LeftThumb) => "LS",
+        (F::Xbox, B::RightThumb) => "RS",
+        (F::PlayStation, B::LeftThumb) => "L3",
+        (F::PlayStation, B::RightThumb) => "R3",
+        (_, B::South) => "South",
+        (_, B::East) => "East",
+        (_, B::West) => "West",
+        (_, B::North) => "North",
+        (_, B::C) => "C",
+        (_, B::Z) => "Z",
+        (_, B::LeftTrigger) => "Left Bumper",
+        (_, B::RightTrigger) => "Right Bumper",
+        (_, B::LeftTrigger2) => "Left Trigger",
+        (_, B::RightTrigger2) => "Right Trigger",
+        (_, B::Select) => "Select",
+        (_, B::Start) => "Start",
+        (_, B::Mode) => "Mode",
+        (_, B::LeftThumb) => "Left Stick Press",
+        (_, B::RightThumb) => "Right Stick Press",
+        (_, B::DPadUp) => "D-Pad Up",
+        (_, B::DPadDown) => "D-Pad Down",
+        (_, B::DPadLeft) => "D-Pad Left",
+        (_, B::DPadRight) => "D-Pad Right",
+    }
+}
+
+fn gesture_name(gesture: TouchGesture) -> &'static str {
+    match gesture {
+        TouchGesture::Tap => "Tap",
+        TouchGesture::SwipeLeft => "Swipe Left",
+        TouchGesture::SwipeRight => "Swipe Right",
+        TouchGesture::SwipeUp => "Swipe Up",
+        TouchGesture::SwipeDown => "Swipe Down",
+        TouchGesture::PinchIn => "Pinch In",
+        TouchGesture::PinchOut => "Pinch Out",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindingNames, DeviceKind, GamepadFamily};
+    use crate::{
+        inputs_vec,
+        test_support::fixtures::{TestAction, TestAxis, TestMap},
+        AxisBinding, ButtonCode,
+    };
+    use bevy::prelude::*;
+    use test_case::test_case;
+
+    #[test_case(GamepadFamily::Generic => "South")]
+    #[test_case(GamepadFamily::Xbox => "A")]
+    #[test_case(GamepadFamily::PlayStation => "Cross")]
+    #[test_case(GamepadFamily::Nintendo => "B")]
+    fn gamepad_button_name(family: GamepadFamily) -> String {
+        BindingNames::default().button_name(GamepadButtonType::South.into(), family)
+    }
+
+    #[test_case(inputs_vec![MouseButton::Left, KeyCode::LControl] => "Ctrl + Left Click")]
+    #[test_case(inputs_vec![KeyCode::S, KeyCode::LAlt, KeyCode::LShift] => "Shift + Alt + S")]
+    #[test_case(inputs_vec![KeyCode::Space] => "Space")]
+    #[cfg_attr(not(target_os = "macos"), test_case(inputs_vec![ButtonCode::ScanCode(17)] => "W"))]
+    #[cfg_attr(not(target_os = "macos"), test_case(inputs_vec![ButtonCode::ScanCode(57)] => "Space"))]
+    #[test_case(inputs_vec![ButtonCode::ScanCode(999)] => "Unknown Key")]
+    fn binding_name(binding: Vec<ButtonCode>) -> String {
+        BindingNames::default().binding_name(&binding.into_iter().collect(), GamepadFamily::Generic)
+    }
+
+    #[test]
+    fn localized_names() {
+        let names = BindingNames::new()
+            .with_button_name(KeyCode::Space, "Leertaste")
+            .with_button_name(KeyCode::LControl, "Strg")
+            .with_gamepad_button_name(GamepadFamily::Xbox, GamepadButtonType::South, "Ⓐ")
+            .with_axis_name(GamepadAxisType::LeftStickX, "Linker Stick X");
+
+        assert_eq!(
+            "Strg + Leertaste",
+            names.binding_name(
+                &inputs_vec![KeyCode::Space, KeyCode::LControl]
+                    .into_iter()
+                    .collect(),
+                GamepadFamily::Generic
+            )
+        );
+        assert_eq!(
+            "Ⓐ",
+            names.button_name(GamepadButtonType::South.into(), GamepadFamily::Xbox)
+        );
+        assert_eq!(
+            "Cross",
+            names.button_name(GamepadButtonType::South.into(), GamepadFamily::PlayStation)
+        );
+        assert_eq!(
+            "Linker Stick X",
+            names.axis_binding_name(
+                &AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+                GamepadFamily::Generic
+            )
+        );
+    }
+
+    #[test]
+    fn prompts() {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, GamepadButtonType::South)
+            .unwrap()
+            .bind_button_combination_action(
+                TestAction::Shoot,
+                inputs_vec![KeyCode::LControl, KeyCode::S],
+            )
+            .unwrap()
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::Buttons(KeyCode::A.into(), KeyCode::D.into()),
+            )
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::Buttons(KeyCode::Left.into(), KeyCode::Right.into()),
+            )
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::Buttons(
+                    GamepadButtonType::DPadLeft.into(),
+                    GamepadButtonType::DPadRight.into(),
+                ),
+            )
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            );
+        let names = BindingNames::default();
+
+        assert_eq!(
+            Some("Space".to_string()),
+            names.prompt_for(
+                &map,
+                TestAction::Jump,
+                DeviceKind::KeyboardMouse,
+                GamepadFamily::Xbox
+            )
+        );
+        assert_eq!(
+            Some("A".to_string()),
+            names.prompt_for(
+                &map,
+                TestAction::Jump,
+                DeviceKind::Gamepad,
+                GamepadFamily::Xbox
+            )
+        );
+        assert_eq!(
+            None,
+            names.prompt_for(
+                &map,
+                TestAction::Shoot,
+                DeviceKind::Gamepad,
+                GamepadFamily::Xbox
+            )
+        );
+        assert_eq!(
+            Some("A / D".to_string()),
+            names.axis_prompt_for(
+                &map,
+                TestAxis::Horizontal,
+                DeviceKind::KeyboardMouse,
+                GamepadFamily::Xbox
+            )
+        );
+        assert_eq!(
+            Some("Left Stick X".to_string()),
+            names.axis_prompt_for(
+                &map,
+                TestAxis::Horizontal,
+                DeviceKind::Gamepad,
+                GamepadFamily::Xbox
+            )
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{
+    action_map::{ActiveKeyData, PlayerData},
+    app_ext::NoAxis,
+    input_time::InputTimestamp,
+    ActionInput, ActionMapInput, ActionState,
+};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// presses and releases seen since the last fixed tick
+#[derive(Debug, Default, Clone, Copy)]
+struct Latch {
+    pressed: bool,
+    released: bool,
+    pressed_at: InputTimestamp,
+    released_at: Option<InputTimestamp>,
+}
+
+// the action states as seen from a fixed timestep stage - presses and releases are latched
+// until the next tick consumes them, so they aren't lost when a frame runs 0 or several ticks
+#[derive(Component)]
+pub struct FixedActionInput<TKeyAction, TAxisAction = NoAxis> {
+    step_seconds: f32,
+    latches: HashMap<PlayerData<TKeyAction>, Latch>,
+    down: HashSet<PlayerData<TKeyAction>>,
+    frame_axes: HashMap<PlayerData<TAxisAction>, f32>,
+    button_actions: HashMap<PlayerData<TKeyAction>, ActionState>,
+    press_timestamps: HashMap<PlayerData<TKeyAction>, InputTimestamp>,
+    axes: HashMap<PlayerData<TAxisAction>, f32>,
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>
+    FixedActionInput<TKeyAction, TAxisAction>
+{
+    // durations are advanced by `step_seconds` every tick
+    pub fn new(step_seconds: f32) -> Self {
+        Self {
+            step_seconds,
+            latches: Default::default(),
+            down: Default::default(),
+            frame_axes: Default::default(),
+            button_actions: Default::default(),
+            press_timestamps: Default::default(),
+            axes: Default::default(),
+        }
+    }
+
+    pub fn step_seconds(&self) -> f32 {
+        self.step_seconds
+    }
+
+    pub fn get_button_action_state(&self, button: TKeyAction) -> Option<&ActionState> {
+        self.button_actions.get(&button.into())
+    }
+
+    pub fn just_pressed(&self, button: TKeyAction) -> bool {
+        matches!(
+            self.get_button_action_state(button),
+            Some(ActionState::Pressed)
+        )
+    }
+
+    pub fn held(&self, button: TKeyAction) -> bool {
+        matches!(
+            self.get_button_action_state(button),
+            Some(ActionState::Held(..))
+        )
+    }
+
+    pub fn just_released(&self, button: TKeyAction) -> bool {
+        matches!(
+            self.get_button_action_state(button),
+            Some(ActionState::Released(..))
+        )
+    }
+
+    pub fn used(&self, button: TKeyAction) -> bool {
+        matches!(
+            self.get_button_action_state(button),
+            Some(ActionState::Used)
+        )
+    }
+
+    pub fn use_button_action(&mut self, button: TKeyAction) {
+        self.button_actions.insert(button.into(), ActionState::Used);
+    }
+
+    pub fn pressed_at(&self, button: TKeyAction) -> Option<InputTimestamp> {
+        let action_data = button.into();
+        match self.button_actions.get(&action_data)? {
+            ActionState::Pressed => self.press_timestamps.get(&action_data).copied(),
+            state => state.pressed_at(),
+        }
+    }
+
+    pub fn released_at(&self, button: TKeyAction) -> Option<InputTimestamp> {
+        self.get_button_action_state(button)?.released_at()
+    }
+
+    pub fn get_axis(&self, axis: &TAxisAction) -> f32 {
+        self.axes
+            .get(&PlayerData::new(*axis))
+            .copied()
+            .unwrap_or(0.)
+    }
+
+    pub fn get_xy_axes_raw(&self, x_axis: &TAxisAction, y_axis: &TAxisAction) -> Vec2 {
+        Vec2::new(self.get_axis(x_axis), self.get_axis(y_axis))
+    }
+
+    pub fn get_xy_axes(&self, x_axis: &TAxisAction, y_axis: &TAxisAction) -> Vec2 {
+        self.get_xy_axes_raw(x_axis, y_axis).normalize_or_zero()
+    }
+
+    fn tick(&mut self) {
+        let actions: HashSet<_> = self
+            .button_actions
+            .keys()
+            .chain(self.latches.keys())
+            .chain(&self.down)
+            .copied()
+            .collect();
+
+        for action in actions {
+            let latch = self.latches.entry(action).or_default();
+            let down = self.down.contains(&action);
+            let current_duration = self
+                .button_actions
+                .get(&action)
+                .map_or(0., ActionState::duration);
+
+            match self.button_actions.get(&action) {
+                None | Some(ActionState::Released(..) | ActionState::Used) => {
+                    if latch.pressed {
+                        // a release latched along with the press is consumed by the next tick
+                        latch.pressed = false;
+                        self.button_actions.insert(action, ActionState::Pressed);
+                        self.press_timestamps.insert(action, latch.pressed_at);
+                    } else {
+                        latch.released = false;
+                        latch.released_at = None;
+                        self.button_actions.remove(&action);
+                        self.press_timestamps.remove(&action);
+                    }
+                }
+                Some(ActionState::Pressed | ActionState::Held(..)) => {
+                    let mut data = ActiveKeyData {
+                        duration: current_duration + self.step_seconds,
+                        pressed_at: self
+                            .press_timestamps
+                            .get(&action)
+                            .copied()
+                            .unwrap_or_default(),
+                        released_at: None,
+                    };
+                    if latch.released || !down {
+                        latch.released = false;
+                        data.released_at = latch.released_at.take();
+                        self.button_actions
+                            .insert(action, ActionState::Released(data));
+                    } else {
+                        self.button_actions.insert(action, ActionState::Held(data));
+                    }
+                }
+            }
+        }
+
+        self.latches
+            .retain(|_, latch| latch.pressed || latch.released);
+        self.axes = self.frame_axes.clone();
+    }
+}
+
+pub(crate) fn latch_fixed_input<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(
+        &ActionInput<TKeyAction, TAxisAction>,
+        &mut FixedActionInput<TKeyAction, TAxisAction>,
+    )>,
+) {
+    for (input, mut fixed) in input_q.iter_mut() {
+        let fixed = &mut *fixed;
+        fixed.down.clear();
+
+        for (action, state) in &input.button_actions {
+            match state {
+                ActionState::Pressed => {
+                    let latch = fixed.latches.entry(*action).or_default();
+                    latch.pressed = true;
+                    latch.pressed_at = input
+                        .press_timestamps
+                        .get(action)
+                        .copied()
+                        .unwrap_or_default();
+                    fixed.down.insert(*action);
+                }
+                ActionState::Held(..) => {
+                    fixed.down.insert(*action);
+                }
+                ActionState::Released(data) => {
+                    let latch = fixed.latches.entry(*action).or_default();
+                    latch.released = true;
+                    latch.released_at = data.released_at;
+                }
+                ActionState::Used => {}
+            }
+        }
+
+        fixed.frame_axes.clone_from(&input.axes);
+    }
+}
+
+// add this to the fixed timestep stage, before the systems reading `FixedActionInput`
+pub fn tick_fixed_input<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<&mut FixedActionInput<TKeyAction, TAxisAction>>,
+) {
+    for mut fixed in input_q.iter_mut() {
+        fixed.tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tick_fixed_input, FixedActionInput};
+    use crate::{
+        test_support::fixtures::{spawn_player, test_app, test_map, TestAction, TestAxis},
+        InputSimulationExt,
+    };
+    use bevy::{ecs::schedule::ShouldRun, prelude::*};
+    use pretty_assertions::assert_eq;
+
+    type TestFixedInput = FixedActionInput<TestAction, TestAxis>;
+
+    // the number of fixed ticks the next frame runs
+    #[derive(Default)]
+    struct PendingTicks(usize);
+
+    // the jump state seen by every fixed tick
+    #[derive(Default)]
+    struct Observed(Vec<String>);
+
+    fn run_ticks(mut ticks: ResMut<PendingTicks>) -> ShouldRun {
+        if ticks.0 == 0 {
+            ShouldRun::No
+        } else {
+            ticks.0 -= 1;
+            ShouldRun::YesAndCheckAgain
+        }
+    }
+
+    fn observe(input_q: Query<&TestFixedInput>, mut observed: ResMut<Observed>) {
+        for input in input_q.iter() {
+            observed.0.push(
+                input
+                    .get_button_action_state(TestAction::Jump)
+                    .map_or("-".to_string(), |state| format!("{:?}", state)),
+            );
+        }
+    }
+
+    fn setup_app() -> App {
+        let mut app = test_app();
+        app.init_resource::<PendingTicks>()
+            .init_resource::<Observed>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(run_ticks)
+                    .with_system(tick_fixed_input::<TestAction, TestAxis>)
+                    .with_system(observe.after(tick_fixed_input::<TestAction, TestAxis>)),
+            );
+        let e = spawn_player(
+            &mut app,
+            test_map([(TestAction::Jump, KeyCode::Space.into())], []),
+        );
+        app.world.entity_mut(e).insert(TestFixedInput::new(0.25));
+        app.advance_frames(1, 0.1);
+        app
+    }
+
+    fn frame(app: &mut App, ticks: usize) {
+        app.world.resource_mut::<PendingTicks>().0 = ticks;
+        app.advance_frames(1, 0.1);
+    }
+
+    fn observed(app: &mut App) -> Vec<String> {
+        std::mem::take(&mut app.world.resource_mut::<Observed>().0)
+    }
+
+    #[test]
+    fn tap_between_ticks() {
+        let mut app = setup_app();
+
+        app.simulate_press(KeyCode::Space);
+        frame(&mut app, 0);
+        app.simulate_release(KeyCode::Space);
+        frame(&mut app, 0);
+        frame(&mut app, 2);
+        frame(&mut app, 1);
+
+        assert_eq!(vec!["Pressed", "Released: 0.25", "-"], observed(&mut app));
+    }
+
+    #[test]
+    fn several_ticks_per_frame() {
+        let mut app = setup_app();
+
+        app.simulate_press(KeyCode::Space);
+        frame(&mut app, 2);
+        frame(&mut app, 0);
+        frame(&mut app, 1);
+        app.simulate_release(KeyCode::Space);
+        frame(&mut app, 3);
+
+        // durations are counted in fixed steps
+        assert_eq!(
+            vec![
+                "Pressed",
+                "Pressed: 0.25",
+                "Pressed: 0.50",
+                "Released: 0.75",
+                "-",
+                "-"
+            ],
+            observed(&mut app)
+        );
+    }
+}
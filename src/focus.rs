@@ -0,0 +1,231 @@
+use crate::{
+    action_map::{ButtonState, PlayerData},
+    ActionInput, ActionMapInput, ButtonCode,
+};
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    prelude::*,
+    window::{WindowFocused, WindowId},
+};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct WindowFocusSettings {
+    // ignores the press that focused the window again, usually a click on it
+    pub suppress_first_press: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct WindowFocusState {
+    reader: ManualEventReader<WindowFocused>,
+    // the last focus change of each window, windows not heard from yet count as focused
+    windows: HashMap<WindowId, bool>,
+    unfocused: bool,
+    suppress_pending: bool,
+}
+
+// releases every button and zeroes the axes when no window has focus - the release events of
+// buttons let go of while unfocused never arrive, so buttons held across the focus change are
+// ignored until they're seen released
+pub(crate) fn handle_window_focus<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<&mut ActionInput<TKeyAction, TAxisAction>>,
+    focus_events: Option<Res<Events<WindowFocused>>>,
+    settings: Res<WindowFocusSettings>,
+    mut state: Local<WindowFocusState>,
+) {
+    let was_unfocused = state.unfocused;
+    if let Some(events) = &focus_events {
+        let state = &mut *state;
+        for ev in state.reader.iter(events) {
+            state.windows.insert(ev.id, ev.focused);
+        }
+        // focus moving between the app's windows doesn't count, whichever event comes first
+        state.unfocused =
+            !state.windows.is_empty() && state.windows.values().all(|focused| !focused);
+    }
+
+    if state.unfocused {
+        state.suppress_pending = false;
+        for mut input in input_q.iter_mut() {
+            let input = &mut *input;
+            if !was_unfocused {
+                let held = input
+                    .button_states
+                    .iter()
+                    .filter(|(_, state)| {
+                        matches!(state, Some(ButtonState::Pressed | ButtonState::Held))
+                    })
+                    .map(|(button, _)| *button);
+                input.suppressed_buttons.extend(held);
+            }
+
+            for state in input.button_states.values_mut() {
+                *state = None;
+            }
+            input.button_timestamps.clear();
+            input.clear_device_axes();
+        }
+
+        return;
+    }
+
+    if was_unfocused && settings.suppress_first_press {
+        state.suppress_pending = true;
+    }
+
+    // the buttons pressed first after focusing again, on any entity
+    let first_pressed: HashSet<PlayerData<ButtonCode>> = if state.suppress_pending {
+        input_q
+            .iter()
+            .flat_map(|input| input.button_states.iter())
+            .filter(|(_, state)| **state == Some(ButtonState::Pressed))
+            .map(|(button, _)| *button)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    if !first_pressed.is_empty() {
+        state.suppress_pending = false;
+    }
+
+    for mut input in input_q.iter_mut() {
+        let input = &mut *input;
+        input
+            .suppressed_buttons
+            .extend(first_pressed.iter().copied());
+
+        let button_states = &mut input.button_states;
+        input.suppressed_buttons.retain(|button| {
+            match button_states.get_mut(button) {
+                Some(state @ Some(ButtonState::Pressed | ButtonState::Held)) => {
+                    *state = None;
+                    true
+                }
+                // the button was let go of, it's reported again from the next press
+                _ => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowFocusSettings;
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestAxis},
+        AxisBinding, InputSimulationExt,
+    };
+    use bevy::{
+        ecs::event::Events,
+        prelude::*,
+        window::{WindowFocused, WindowId},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn setup_app(suppress_first_press: bool) -> (App, Entity) {
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Space.into()),
+                (TestAction::Shoot, MouseButton::Left.into()),
+            ],
+            [(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )],
+        );
+
+        let mut app = test_app();
+        app.add_event::<WindowFocused>()
+            .insert_resource(WindowFocusSettings {
+                suppress_first_press,
+            });
+        let e = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.1);
+        (app, e)
+    }
+
+    fn set_focus(app: &mut App, focused: bool) {
+        set_window_focus(app, WindowId::primary(), focused);
+    }
+
+    fn set_window_focus(app: &mut App, id: WindowId, focused: bool) {
+        app.world
+            .resource_mut::<Events<WindowFocused>>()
+            .send(WindowFocused { id, focused });
+    }
+
+    fn state(app: &App, e: Entity, action: TestAction) -> String {
+        input(app, e)
+            .get_button_action_state(action)
+            .map_or("-".to_string(), |state| format!("{:?}", state))
+    }
+
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn focus_loss_releases_everything() {
+        let (mut app, e) = setup_app(false);
+
+        app.simulate_press(KeyCode::Space)
+            .simulate_axis(GamepadAxisType::LeftStickX, 0.5);
+        app.advance_frames(2, 0.1);
+        assert_eq!("Pressed: 0.10", state(&app, e, TestAction::Jump));
+
+        set_focus(&mut app, false);
+        app.advance_frames(1, 0.1);
+        assert_eq!("Released: 0.20", state(&app, e, TestAction::Jump));
+        assert_eq!(0., input(&app, e).get_axis(&TestAxis::Horizontal));
+
+        // the release of the key never arrives
+        app.advance_frames(1, 0.1);
+        set_focus(&mut app, true);
+        app.advance_frames(2, 0.1);
+        assert_eq!("-", state(&app, e, TestAction::Jump));
+
+        app.simulate_release(KeyCode::Space);
+        app.advance_frames(1, 0.1);
+        app.simulate_press(KeyCode::Space);
+        app.advance_frames(1, 0.1);
+        assert_eq!("Pressed", state(&app, e, TestAction::Jump));
+    }
+
+    #[test]
+    fn first_press_after_focus_is_suppressed() {
+        let (mut app, e) = setup_app(true);
+
+        set_focus(&mut app, false);
+        app.advance_frames(1, 0.1);
+        set_focus(&mut app, true);
+        app.advance_frames(1, 0.1);
+
+        // the click focusing the window
+        app.simulate_press(MouseButton::Left);
+        app.advance_frames(2, 0.1);
+        assert_eq!("-", state(&app, e, TestAction::Shoot));
+
+        app.simulate_release(MouseButton::Left);
+        app.advance_frames(1, 0.1);
+        app.simulate_press(MouseButton::Left);
+        app.advance_frames(1, 0.1);
+        assert_eq!("Pressed", state(&app, e, TestAction::Shoot));
+    }
+
+    #[test]
+    fn focus_moving_to_another_window_keeps_input() {
+        let (mut app, e) = setup_app(false);
+        let secondary = WindowId::new();
+
+        app.simulate_press(KeyCode::Space);
+        app.advance_frames(1, 0.1);
+        set_window_focus(&mut app, secondary, true);
+        set_focus(&mut app, false);
+        app.advance_frames(1, 0.1);
+        assert_eq!("Pressed: 0.10", state(&app, e, TestAction::Jump));
+
+        set_window_focus(&mut app, secondary, false);
+        app.advance_frames(1, 0.1);
+        assert_eq!("Released: 0.20", state(&app, e, TestAction::Jump));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::MapIoError;
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapFormat {
+    #[default]
+    Ron,
+    Json,
+    Toml,
+    // compact, not human readable
+    Binary,
+}
+
+impl MapFormat {
+    // `.bindings` and unknown extensions are read as RON
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("json") => Self::Json,
+            Some("toml") => Self::Toml,
+            Some("bin") => Self::Binary,
+            _ => Self::Ron,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ron => "bindings",
+            Self::Json => "json",
+            Self::Toml => "toml",
+            Self::Binary => "bin",
+        }
+    }
+
+    pub(crate) fn serialize<T: Serialize>(self, value: &T) -> Result<Vec<u8>, MapIoError> {
+        let res = match self {
+            Self::Ron => ron::ser::to_string_pretty(value, PrettyConfig::default())
+                .map(String::into_bytes)
+                .map_err(|err| err.to_string()),
+            Self::Json => serde_json::to_vec_pretty(value).map_err(|err| err.to_string()),
+            Self::Toml => toml::to_string_pretty(value)
+                .map(String::into_bytes)
+                .map_err(|err| err.to_string()),
+            Self::Binary => bincode::serialize(value).map_err(|err| err.to_string()),
+        };
+
+        res.map_err(MapIoError::Serialize)
+    }
+
+    // line and column are 1-based, 0 when the format doesn't track them
+    pub(crate) fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, MapIoError> {
+        match self {
+            Self::Ron => Ok(ron::de::from_bytes(bytes)?),
+            Self::Json => serde_json::from_slice(bytes).map_err(|err| MapIoError::Parse {
+                message: err.to_string(),
+                line: err.line(),
+                column: err.column(),
+            }),
+            Self::Toml => {
+                let text = std::str::from_utf8(bytes).map_err(|err| MapIoError::Parse {
+                    message: err.to_string(),
+                    line: 0,
+                    column: 0,
+                })?;
+                toml::from_str(text).map_err(|err| {
+                    let (line, column) =
+                        err.span().map_or((0, 0), |span| line_col(text, span.start));
+                    MapIoError::Parse {
+                        message: err.message().to_string(),
+                        line,
+                        column,
+                    }
+                })
+            }
+            Self::Binary => bincode::deserialize(bytes).map_err(|err| MapIoError::Parse {
+                message: err.to_string(),
+                line: 0,
+                column: 0,
+            }),
+        }
+    }
+}
+
+// 1-based line and column of the byte offset
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapFormat;
+    use crate::{
+        test_support::fixtures::{TestAction, TestAxis, TestMap},
+        versioning::BindingsFile,
+        AxisBinding, BindingsMigrations, ButtonCode, MapIoError, TouchRegion, TouchStick,
+    };
+    use bevy::prelude::*;
+    use test_case::test_case;
+
+    fn full_map() -> TestMap {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, GamepadButtonType::South)
+            .unwrap()
+            .bind_button_combination_action(
+                TestAction::Shoot,
+                [KeyCode::LControl.into(), MouseButton::Left.into()],
+            )
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, ButtonCode::ScanCode(29))
+            .unwrap()
+            .bind_button_action(TestAction::Crouch, KeyCode::C)
+            .unwrap()
+            .bind_button_action(TestAction::Crouch, GamepadButtonType::East)
+            .unwrap()
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::Buttons(KeyCode::A.into(), KeyCode::D.into()),
+            )
+            .bind_axis_with_deadzone(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+                0.15,
+            )
+            .bind_axis(
+                TestAxis::Vertical,
+                AxisBinding::TouchStickY(TouchStick::new(TouchRegion::new(0, 0, 200, 200), 50)),
+            );
+        map
+    }
+
+    #[test_case("keys.bindings" => MapFormat::Ron)]
+    #[test_case("keys.ron" => MapFormat::Ron)]
+    #[test_case("keys.JSON" => MapFormat::Json)]
+    #[test_case("dir.json/keys.toml" => MapFormat::Toml)]
+    #[test_case("keys.bin" => MapFormat::Binary)]
+    #[test_case("keys" => MapFormat::Ron)]
+    fn format_from_path(path: &str) -> MapFormat {
+        MapFormat::from_path(path)
+    }
+
+    #[test_case(MapFormat::Ron)]
+    #[test_case(MapFormat::Json)]
+    #[test_case(MapFormat::Toml)]
+    #[test_case(MapFormat::Binary)]
+    fn round_trip(format: MapFormat) {
+        let map = full_map();
+        let bytes = format
+            .serialize(&BindingsFile::from_map(&map, &TestMap::default(), 1).unwrap())
+            .unwrap();
+        let loaded: TestMap = format
+            .deserialize::<BindingsFile>(&bytes)
+            .unwrap()
+            .into_map(&TestMap::default(), &BindingsMigrations::default(), false)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(map.get_key_bindings(), loaded.get_key_bindings());
+        pretty_assertions::assert_eq!(map.get_axis_bindings(), loaded.get_axis_bindings());
+    }
+
+    #[test_case(MapFormat::Ron)]
+    #[test_case(MapFormat::Json)]
+    #[test_case(MapFormat::Toml)]
+    #[test_case(MapFormat::Binary)]
+    fn deterministic_save(format: MapFormat) {
+        let save = |map: &TestMap| {
+            format
+                .serialize(&BindingsFile::from_map(map, &TestMap::default(), 1).unwrap())
+                .unwrap()
+        };
+        let bytes = save(&full_map());
+        let loaded: TestMap = format
+            .deserialize::<BindingsFile>(&bytes)
+            .unwrap()
+            .into_map(&TestMap::default(), &BindingsMigrations::default(), false)
+            .unwrap();
+
+        assert_eq!(bytes, save(&loaded));
+        // maps built separately iterate their bindings in a different order
+        assert_eq!(bytes, save(&full_map()));
+    }
+
+    #[test]
+    fn actions_in_declaration_order() {
+        let bytes = MapFormat::Ron
+            .serialize(&BindingsFile::from_map(&full_map(), &TestMap::default(), 1).unwrap())
+            .unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let position = |action: &str| text.find(action).unwrap();
+        assert!(position("Jump") < position("Shoot"));
+        assert!(position("Shoot") < position("Crouch"));
+        assert!(text.contains(
+            r#""Crouch": [
+            "C",
+            "Pad:East",
+        ]"#
+        ));
+    }
+
+    #[test_case(MapFormat::Ron, "(\n    version: 1,\n    keys: {\n        oops\n" => (4, 9))]
+    #[test_case(MapFormat::Json, "{\n  \"version\": 1,\n  \"keys\": {\n    oops\n" => (4, 5))]
+    #[test_case(MapFormat::Toml, "version = 1\nkeys = oops\n" => (2, 8))]
+    fn parse_error_position(format: MapFormat, text: &str) -> (usize, usize) {
+        match format.deserialize::<BindingsFile>(text.as_bytes()) {
+            Err(MapIoError::Parse { line, column, .. }) => (line, column),
+            res => panic!("unexpected result {:?}", res.map(|_| ())),
+        }
+    }
+}
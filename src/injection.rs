@@ -0,0 +1,306 @@
+use crate::{
+    action_map::{ButtonState, PlayerData},
+    app_ext::NoAxis,
+    ActionMapInput,
+};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// how injected input is combined with the input of the bound devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InjectionPolicy {
+    // injected actions replace the device input, injected releases block it
+    #[default]
+    Override,
+    // an action is down if it's down on either side, axis values are summed
+    Additive,
+    // injected input is only used while the bound devices are idle
+    DeviceWins,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectionDuration {
+    // the number of processed frames, at least one
+    Frames(u32),
+    UntilCleared,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Injected<T> {
+    value: T,
+    remaining: InjectionDuration,
+    fresh: bool,
+}
+
+impl<T> Injected<T> {
+    fn new(value: T, remaining: InjectionDuration) -> Self {
+        Self {
+            value,
+            remaining,
+            fresh: true,
+        }
+    }
+
+    // returns false once the injection has run out
+    fn advance(&mut self) -> bool {
+        self.fresh = false;
+        match &mut self.remaining {
+            InjectionDuration::Frames(frames) if *frames <= 1 => false,
+            InjectionDuration::Frames(frames) => {
+                *frames -= 1;
+                true
+            }
+            InjectionDuration::UntilCleared => true,
+        }
+    }
+}
+
+// drives the `ActionInput` of the same entity without a device, e.g. for bots or cutscenes
+#[derive(Component, Debug, Clone)]
+pub struct InjectedInput<TKeyAction, TAxisAction = NoAxis> {
+    policy: InjectionPolicy,
+    buttons: HashMap<PlayerData<TKeyAction>, Injected<bool>>,
+    axes: HashMap<PlayerData<TAxisAction>, Injected<f32>>,
+}
+
+impl<TKeyAction, TAxisAction> Default for InjectedInput<TKeyAction, TAxisAction> {
+    fn default() -> Self {
+        Self {
+            policy: Default::default(),
+            buttons: Default::default(),
+            axes: Default::default(),
+        }
+    }
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>
+    InjectedInput<TKeyAction, TAxisAction>
+{
+    pub fn new(policy: InjectionPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> InjectionPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: InjectionPolicy) {
+        self.policy = policy;
+    }
+
+    // the action is just pressed on the next processed frame and held for the rest of the duration
+    pub fn press(&mut self, action: TKeyAction, duration: InjectionDuration) -> &mut Self {
+        self.buttons
+            .insert(action.into(), Injected::new(true, duration));
+        self
+    }
+
+    // keeps the action up, releasing it if it's held
+    pub fn release(&mut self, action: TKeyAction, duration: InjectionDuration) -> &mut Self {
+        self.buttons
+            .insert(action.into(), Injected::new(false, duration));
+        self
+    }
+
+    pub fn set_axis(
+        &mut self,
+        axis: TAxisAction,
+        value: f32,
+        duration: InjectionDuration,
+    ) -> &mut Self {
+        self.axes
+            .insert(axis.into(), Injected::new(value.clamp(-1., 1.), duration));
+        self
+    }
+
+    pub fn clear_action(&mut self, action: TKeyAction) -> &mut Self {
+        self.buttons.remove(&action.into());
+        self
+    }
+
+    pub fn clear_axis(&mut self, axis: TAxisAction) -> &mut Self {
+        self.axes.remove(&axis.into());
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.buttons.clear();
+        self.axes.clear();
+    }
+
+    pub fn is_injected(&self, action: TKeyAction) -> bool {
+        self.buttons.contains_key(&action.into())
+    }
+
+    pub(crate) fn injected_actions(&self) -> impl Iterator<Item = &PlayerData<TKeyAction>> {
+        self.buttons.keys()
+    }
+
+    pub(crate) fn injected_axes(&self) -> impl Iterator<Item = &PlayerData<TAxisAction>> {
+        self.axes.keys()
+    }
+
+    pub(crate) fn merge_button(
+        &self,
+        action: &PlayerData<TKeyAction>,
+        device_state: Option<ButtonState>,
+    ) -> Option<ButtonState> {
+        let injected = match self.buttons.get(action) {
+            Some(injected) => injected,
+            None => return device_state,
+        };
+        let injected_state = match (injected.value, injected.fresh) {
+            (true, true) => Some(ButtonState::Pressed),
+            (true, false) => Some(ButtonState::Held),
+            (false, _) => None,
+        };
+
+        match self.policy {
+            InjectionPolicy::Override => injected_state,
+            InjectionPolicy::Additive => {
+                if device_state == Some(ButtonState::Pressed) {
+                    device_state
+                } else {
+                    injected_state.or(device_state)
+                }
+            }
+            InjectionPolicy::DeviceWins => device_state.or(injected_state),
+        }
+    }
+
+    pub(crate) fn merge_axis(&self, axis: &PlayerData<TAxisAction>, device_value: f32) -> f32 {
+        let injected = match self.axes.get(axis) {
+            Some(injected) => injected.value,
+            None => return device_value,
+        };
+
+        match self.policy {
+            InjectionPolicy::Override => injected,
+            InjectionPolicy::Additive => (device_value + injected).clamp(-1., 1.),
+            InjectionPolicy::DeviceWins if device_value == 0. => injected,
+            InjectionPolicy::DeviceWins => device_value,
+        }
+    }
+
+    pub(crate) fn advance_buttons(&mut self) {
+        self.buttons.retain(|_, injected| injected.advance());
+    }
+
+    pub(crate) fn advance_axes(&mut self) {
+        self.axes.retain(|_, injected| injected.advance());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InjectedInput, InjectionDuration, InjectionPolicy};
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestAxis},
+        AxisBinding, InputSimulationExt,
+    };
+    use bevy::prelude::*;
+    use test_case::test_case;
+
+    type TestInjection = InjectedInput<TestAction, TestAxis>;
+
+    fn setup_app(policy: InjectionPolicy) -> (App, Entity) {
+        let map = test_map(
+            [(TestAction::Jump, KeyCode::Space.into())],
+            [(
+                TestAxis::Horizontal,
+                AxisBinding::Buttons(KeyCode::A.into(), KeyCode::D.into()),
+            )],
+        );
+
+        let mut app = test_app();
+        let e = spawn_player(&mut app, map);
+        app.world.entity_mut(e).insert(TestInjection::new(policy));
+        app.advance_frames(1, 0.1);
+        (app, e)
+    }
+
+    fn injection(app: &mut App, e: Entity) -> Mut<'_, TestInjection> {
+        app.world.get_mut::<TestInjection>(e).unwrap()
+    }
+
+    #[test]
+    fn press_for_frames() {
+        let (mut app, e) = setup_app(InjectionPolicy::Override);
+
+        // unbound actions can be injected too
+        injection(&mut app, e)
+            .press(TestAction::Jump, InjectionDuration::Frames(2))
+            .press(TestAction::Interact, InjectionDuration::Frames(2));
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e).just_pressed(TestAction::Jump));
+        assert!(input(&app, e).just_pressed(TestAction::Interact));
+
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e).held(TestAction::Jump));
+        assert!(input(&app, e).held(TestAction::Interact));
+
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e).just_released(TestAction::Jump));
+        assert!(input(&app, e).just_released(TestAction::Interact));
+        assert!(!injection(&mut app, e).is_injected(TestAction::Jump));
+
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e)
+            .get_button_action_state(TestAction::Interact)
+            .is_none());
+    }
+
+    #[test]
+    fn until_cleared() {
+        let (mut app, e) = setup_app(InjectionPolicy::Override);
+
+        injection(&mut app, e).press(TestAction::Jump, InjectionDuration::UntilCleared);
+        app.advance_frames(5, 0.1);
+        assert!(input(&app, e).held(TestAction::Jump));
+
+        injection(&mut app, e).clear_action(TestAction::Jump);
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e).just_released(TestAction::Jump));
+    }
+
+    // (held by the device after an injected release, axis with the device at 1 and 0.5 injected)
+    #[allow(clippy::float_cmp)]
+    #[test_case(InjectionPolicy::Override => (false, 0.5))]
+    #[test_case(InjectionPolicy::Additive => (true, 1.))]
+    #[test_case(InjectionPolicy::DeviceWins => (true, 1.))]
+    fn policies(policy: InjectionPolicy) -> (bool, f32) {
+        let (mut app, e) = setup_app(policy);
+
+        app.simulate_press(KeyCode::Space)
+            .simulate_press(KeyCode::D)
+            .advance_frames(1, 0.1);
+        injection(&mut app, e)
+            .release(TestAction::Jump, InjectionDuration::UntilCleared)
+            .set_axis(TestAxis::Horizontal, 0.5, InjectionDuration::UntilCleared);
+        app.advance_frames(1, 0.1);
+
+        (
+            input(&app, e).held(TestAction::Jump),
+            input(&app, e).get_axis(&TestAxis::Horizontal),
+        )
+    }
+
+    #[allow(clippy::float_cmp)]
+    #[test_case(InjectionPolicy::Override => -0.5)]
+    #[test_case(InjectionPolicy::Additive => -0.5)]
+    #[test_case(InjectionPolicy::DeviceWins => -0.5)]
+    fn idle_device(policy: InjectionPolicy) -> f32 {
+        let (mut app, e) = setup_app(policy);
+
+        injection(&mut app, e).set_axis(TestAxis::Horizontal, -0.5, InjectionDuration::Frames(1));
+        app.advance_frames(1, 0.1);
+        let value = input(&app, e).get_axis(&TestAxis::Horizontal);
+
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e).get_axis(&TestAxis::Horizontal).abs() < f32::EPSILON);
+        value
+    }
+}
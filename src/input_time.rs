@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+// the clock action durations and touch gestures are measured with,
+// follows `Time` unless it's advanced manually
+#[derive(Debug, Clone, Default)]
+pub struct InputTime {
+    delta_seconds: f32,
+    seconds_since_startup: f64,
+    manual: bool,
+}
+
+impl InputTime {
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta_seconds
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.seconds_since_startup
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.manual
+    }
+
+    // stops following `Time`, the delta of the next frame is set by `advance`
+    pub fn set_manual(&mut self, manual: bool) {
+        self.manual = manual;
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        self.delta_seconds = delta_seconds;
+        self.seconds_since_startup += f64::from(delta_seconds);
+    }
+
+    // the timestamp of transitions that don't come from an ordered device event
+    pub fn now(&self) -> InputTimestamp {
+        InputTimestamp::new(self.seconds_since_startup, 0)
+    }
+}
+
+// when an action transition happened - the frame the device event arrived in and the position of
+// the event within that frame, so transitions of the same frame can still be ordered
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputTimestamp {
+    pub seconds: f64,
+    pub ordinal: u32,
+}
+
+impl InputTimestamp {
+    pub fn new(seconds: f64, ordinal: u32) -> Self {
+        Self { seconds, ordinal }
+    }
+}
+
+pub(crate) fn sync_input_time(time: Res<Time>, mut input_time: ResMut<InputTime>) {
+    if !input_time.manual {
+        input_time.delta_seconds = time.delta_seconds();
+        input_time.seconds_since_startup = time.seconds_since_startup();
+    }
+}
@@ -1,5 +1,6 @@
 #![feature(if_let_guard)]
 #![feature(trait_alias)]
+#![cfg_attr(feature = "serialize", feature(const_type_name))]
 #![warn(clippy::pedantic)]
 #![allow(clippy::default_trait_access)]
 #![allow(clippy::module_name_repetitions)]
@@ -9,18 +10,83 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::cast_precision_loss)]
 
+mod action_events;
 mod action_map;
 mod app_ext;
 #[cfg(feature = "serialize")]
+mod binding_syntax;
+#[cfg(feature = "serialize")]
 mod bindings_loader;
+mod button_events;
+mod device;
+mod display;
+mod fixed_timestep;
+mod focus;
+#[cfg(feature = "serialize")]
+mod format;
+mod injection;
+mod input_time;
+#[cfg(feature = "serialize")]
+mod location;
 mod macros;
+#[cfg(feature = "serialize")]
+mod map_asset;
+mod muting;
+mod pointer;
+#[cfg(feature = "serialize")]
+mod profiles;
+#[cfg(feature = "serialize")]
+mod recording;
+mod remote;
+mod snapshot;
+#[cfg(any(test, feature = "test-support"))]
+mod test_support;
+mod touch;
 mod validation;
+#[cfg(feature = "serialize")]
+mod versioning;
 
+pub use action_events::{ActionEvent, ActionTransition};
 pub use action_map::{
     ActionInput, ActionMap, ActionMapInput, ActionState, AxisBinding, ButtonCode, InputGamepad,
 };
+#[cfg(feature = "serialize")]
+pub use action_map::SerializableActionInput;
 pub use app_ext::AppActionInputExt;
+pub use button_events::ButtonStateSettings;
+pub use device::{ActiveDeviceChanged, ActiveDeviceSettings, InputDevice};
+pub use display::{BindingNames, DeviceKind, GamepadFamily};
+pub use fixed_timestep::{tick_fixed_input, FixedActionInput};
+pub use focus::WindowFocusSettings;
 #[cfg(feature = "serialize")]
-pub use bindings_loader::{MapIoEvent, MapIoRequest};
+pub use bindings_loader::{
+    DefaultActionMap, LoadFailurePolicy, MapIoError, MapIoEvent, MapIoKind, MapIoRequest,
+    MapIoRequestId, MapIoSettings,
+};
+#[cfg(feature = "serialize")]
+pub use format::MapFormat;
+pub use injection::{InjectedInput, InjectionDuration, InjectionPolicy};
+pub use input_time::{InputTime, InputTimestamp};
+#[cfg(feature = "serialize")]
+pub use location::{BindingsLocation, BindingsPaths};
 pub use macros::*;
+#[cfg(feature = "serialize")]
+pub use map_asset::{ActionMapLoader, MapAssetExtensions};
+pub use muting::InputMuted;
+pub use pointer::{screen_to_world_2d, Pointer, PointerCamera};
+#[cfg(feature = "serialize")]
+pub use profiles::{
+    BindingsProfile, BindingsProfiles, ProfileEvent, ProfilePlayer, ProfileRequest,
+};
+#[cfg(feature = "serialize")]
+pub use recording::{InputRecorder, InputRecording, InputReplay, RecordedFrame};
+#[cfg(any(test, feature = "test-support"))]
+pub use remote::LoopbackTransport;
+pub use remote::{RemoteInput, RemoteInputSender, RemotePacket};
+pub use snapshot::{ActionIndices, InputSnapshot, MAX_SNAPSHOT_ACTIONS, MAX_SNAPSHOT_AXES};
+#[cfg(any(test, feature = "test-support"))]
+pub use test_support::{headless_input_app, InputSimulationExt, SIMULATED_GAMEPAD};
+pub use touch::{TouchGesture, TouchGestureSettings, TouchRegion, TouchStick};
 pub use validation::BindingError;
+#[cfg(feature = "serialize")]
+pub use versioning::{BindingsMigrations, Migration, MigrationStep};
@@ -0,0 +1,242 @@
+use crate::MapIoError;
+use bevy::{asset::AssetServerSettings, prelude::*};
+use futures_lite::AsyncWriteExt;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_TMP_ID: AtomicU64 = AtomicU64::new(0);
+
+// where a bindings file is read from or written to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BindingsLocation {
+    // relative to `<platform config dir>/<app name>`,
+    // e.g. `~/.config` on Linux or `%APPDATA%` on Windows
+    UserConfig(PathBuf),
+    // relative to the asset folder
+    Assets(PathBuf),
+    Path(PathBuf),
+}
+
+impl BindingsLocation {
+    pub fn user_config(path: impl Into<PathBuf>) -> Self {
+        Self::UserConfig(path.into())
+    }
+
+    pub fn assets(path: impl Into<PathBuf>) -> Self {
+        Self::Assets(path.into())
+    }
+
+    // the path relative to the location's root
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::UserConfig(path) | Self::Assets(path) | Self::Path(path) => path,
+        }
+    }
+
+    #[must_use]
+    pub fn join(&self, path: impl AsRef<Path>) -> Self {
+        match self {
+            Self::UserConfig(root) => Self::UserConfig(root.join(path)),
+            Self::Assets(root) => Self::Assets(root.join(path)),
+            Self::Path(root) => Self::Path(root.join(path)),
+        }
+    }
+}
+
+impl From<PathBuf> for BindingsLocation {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&Path> for BindingsLocation {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
+}
+
+impl From<String> for BindingsLocation {
+    fn from(path: String) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+impl From<&str> for BindingsLocation {
+    fn from(path: &str) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+// resolves `BindingsLocation`s to file paths
+#[derive(Debug, Clone)]
+pub struct BindingsPaths {
+    // user config files are stored in a directory of this name
+    pub app_name: String,
+    pub asset_folder: PathBuf,
+}
+
+// the app name defaults to the executable name and the asset folder to the one of the `AssetServer`
+impl FromWorld for BindingsPaths {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            app_name: std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.file_stem()?.to_str().map(String::from))
+                .unwrap_or_else(|| "bevy".to_string()),
+            asset_folder: world.get_resource::<AssetServerSettings>().map_or_else(
+                || "assets".into(),
+                |settings| settings.asset_folder.clone().into(),
+            ),
+        }
+    }
+}
+
+impl BindingsPaths {
+    /// # Errors
+    ///
+    /// Will return an `Err` if the platform has no user config directory
+    pub fn resolve(&self, location: &BindingsLocation) -> Result<PathBuf, MapIoError> {
+        match location {
+            BindingsLocation::UserConfig(path) => dirs::config_dir()
+                .map(|dir| dir.join(&self.app_name).join(path))
+                .ok_or(MapIoError::NoConfigDir),
+            BindingsLocation::Assets(path) => Ok(self.asset_folder.join(path)),
+            BindingsLocation::Path(path) => Ok(path.clone()),
+        }
+    }
+}
+
+// writes to a temporary file first and renames it over the target, so an interrupted save
+// never leaves a truncated file behind, the previous file is kept as `<name>.bak` when `backup`
+pub(crate) async fn write_atomic(
+    path: PathBuf,
+    bytes: Vec<u8>,
+    backup: bool,
+) -> Result<(), MapIoError> {
+    if let Some(dir) = path.parent() {
+        async_fs::create_dir_all(dir).await?;
+    }
+
+    // unique so concurrent saves to the same path don't write into each other's temporary file
+    let tmp_path = with_extra_extension(
+        &path,
+        &format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            NEXT_TMP_ID.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let res = replace_with(&path, &tmp_path, &bytes, backup).await;
+    if res.is_err() {
+        let _ = async_fs::remove_file(&tmp_path).await;
+    }
+    res
+}
+
+async fn replace_with(
+    path: &Path,
+    tmp_path: &Path,
+    bytes: &[u8],
+    backup: bool,
+) -> Result<(), MapIoError> {
+    let mut file = async_fs::File::create(tmp_path).await?;
+    file.write_all(bytes).await?;
+    // the rename can reach the disk before the data, leaving an empty file after a crash
+    file.sync_all().await?;
+    drop(file);
+
+    if backup && async_fs::metadata(path).await.is_ok() {
+        async_fs::copy(path, with_extra_extension(path, "bak")).await?;
+    }
+
+    async_fs::rename(tmp_path, path).await?;
+    Ok(())
+}
+
+fn with_extra_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_atomic, BindingsLocation, BindingsPaths};
+    use futures_lite::future;
+    use std::path::PathBuf;
+    use test_case::test_case;
+
+    fn paths() -> BindingsPaths {
+        BindingsPaths {
+            app_name: "game".to_string(),
+            asset_folder: "assets".into(),
+        }
+    }
+
+    #[test_case(BindingsLocation::assets("bindings/default.bindings") => PathBuf::from("assets/bindings/default.bindings"))]
+    #[test_case(BindingsLocation::from("keys.json") => PathBuf::from("keys.json"))]
+    #[test_case(BindingsLocation::assets("bindings").join("p1.toml") => PathBuf::from("assets/bindings/p1.toml"))]
+    fn resolve(location: BindingsLocation) -> PathBuf {
+        paths().resolve(&location).unwrap()
+    }
+
+    #[test]
+    fn resolve_user_config() {
+        if let Some(config_dir) = dirs::config_dir() {
+            assert_eq!(
+                config_dir.join("game").join("keys.bindings"),
+                paths()
+                    .resolve(&BindingsLocation::user_config("keys.bindings"))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn atomic_write_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("bevy_input_{}_atomic", std::process::id()));
+        let path = dir.join("nested").join("keys.bindings");
+
+        future::block_on(write_atomic(path.clone(), b"first".to_vec(), true)).unwrap();
+        future::block_on(write_atomic(path.clone(), b"second".to_vec(), true)).unwrap();
+
+        assert_eq!(b"second".to_vec(), std::fs::read(&path).unwrap());
+        assert_eq!(
+            b"first".to_vec(),
+            std::fs::read(dir.join("nested").join("keys.bindings.bak")).unwrap()
+        );
+        // no temporary files are left behind
+        assert_eq!(2, std::fs::read_dir(dir.join("nested")).unwrap().count());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_atomic_writes() {
+        let dir =
+            std::env::temp_dir().join(format!("bevy_input_{}_concurrent", std::process::id()));
+        let path = dir.join("keys.bindings");
+        let contents: Vec<_> = (0..8).map(|i| vec![i; 64 * 1024]).collect();
+
+        let threads: Vec<_> = contents
+            .iter()
+            .cloned()
+            .map(|bytes| {
+                let path = path.clone();
+                std::thread::spawn(move || future::block_on(write_atomic(path, bytes, false)))
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap().unwrap();
+        }
+
+        // one of the writes wins completely
+        assert!(contents.contains(&std::fs::read(&path).unwrap()));
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,455 @@
+use crate::{
+    app_ext::NoAxis, versioning::BindingsFile, ActionInput, ActionMap, BindingsMigrations,
+    DefaultActionMap, MapFormat, MapIoSettings, SerializableActionInput,
+};
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::HashSet,
+};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+struct LoaderConfig<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> {
+    defaults: ActionMap<TKeyAction, TAxisAction>,
+    migrations: BindingsMigrations,
+    lenient: bool,
+}
+
+// shared with the `ActionMapLoader`, kept in sync with the `DefaultActionMap`, `BindingsMigrations`
+// and `MapIoSettings` resources so assets always load with the current ones
+pub(crate) struct MapLoaderConfig<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput,
+>(Arc<RwLock<LoaderConfig<TKeyAction, TAxisAction>>>);
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> Default
+    for MapLoaderConfig<TKeyAction, TAxisAction>
+{
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(LoaderConfig {
+            defaults: ActionMap::default(),
+            migrations: BindingsMigrations::default(),
+            lenient: false,
+        })))
+    }
+}
+
+// the file extensions `ActionMap` assets of the type pair are loaded from, `bindings` by default -
+// insert before `add_action_serialize_systems` to give every registered type pair its own, e.g.
+// `menu.bindings`, an extension can only be claimed by one type pair
+pub struct MapAssetExtensions<TKeyAction, TAxisAction = NoAxis> {
+    extensions: Vec<&'static str>,
+    marker: PhantomData<fn() -> (TKeyAction, TAxisAction)>,
+}
+
+impl<TKeyAction, TAxisAction> MapAssetExtensions<TKeyAction, TAxisAction> {
+    pub fn new(extensions: impl IntoIterator<Item = &'static str>) -> Self {
+        Self {
+            extensions: extensions.into_iter().collect(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn extensions(&self) -> &[&'static str] {
+        &self.extensions
+    }
+}
+
+impl<TKeyAction, TAxisAction> Default for MapAssetExtensions<TKeyAction, TAxisAction> {
+    fn default() -> Self {
+        Self::new(["bindings"])
+    }
+}
+
+// the type pair that claimed each extension
+#[derive(Default)]
+pub(crate) struct ClaimedMapExtensions(HashMap<&'static str, &'static str>);
+
+// drops the extensions of the type pair another one has claimed already, as the asset server
+// would only use the loader registered last for them
+pub(crate) fn claim_map_extensions<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    world: &mut World,
+) {
+    let type_name = std::any::type_name::<ActionMap<TKeyAction, TAxisAction>>();
+    let extensions = world
+        .get_resource_or_insert_with(MapAssetExtensions::<TKeyAction, TAxisAction>::default)
+        .extensions
+        .clone();
+    let mut claimed = world.get_resource_or_insert_with(ClaimedMapExtensions::default);
+
+    let free: Vec<_> = extensions
+        .into_iter()
+        .filter(|extension| {
+            let owner = *claimed.0.entry(extension).or_insert(type_name);
+            if owner != type_name {
+                warn!(
+                    "`.{}` assets are already loaded as `{}`, insert `MapAssetExtensions` with \
+                    another extension for `{}`",
+                    extension, owner, type_name
+                );
+            }
+            owner == type_name
+        })
+        .collect();
+    world.insert_resource(MapAssetExtensions::<TKeyAction, TAxisAction>::new(free));
+}
+
+// loads RON bindings files with the type pair's `MapAssetExtensions` as `ActionMap` assets
+pub struct ActionMapLoader<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput,
+> {
+    config: Arc<RwLock<LoaderConfig<TKeyAction, TAxisAction>>>,
+    extensions: Vec<&'static str>,
+}
+
+impl<
+        TKeyAction: SerializableActionInput + 'static,
+        TAxisAction: SerializableActionInput + 'static,
+    > FromWorld for ActionMapLoader<TKeyAction, TAxisAction>
+{
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            config: world
+                .get_resource_or_insert_with(MapLoaderConfig::<TKeyAction, TAxisAction>::default)
+                .0
+                .clone(),
+            extensions: world
+                .get_resource_or_insert_with(MapAssetExtensions::<TKeyAction, TAxisAction>::default)
+                .extensions
+                .clone(),
+        }
+    }
+}
+
+impl<
+        TKeyAction: SerializableActionInput + 'static,
+        TAxisAction: SerializableActionInput + 'static,
+    > AssetLoader for ActionMapLoader<TKeyAction, TAxisAction>
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let file = MapFormat::Ron.deserialize::<BindingsFile>(bytes)?;
+            let map = {
+                let config = self.config.read().unwrap_or_else(PoisonError::into_inner);
+                file.into_map(&config.defaults, &config.migrations, config.lenient)?
+            };
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+pub(crate) fn sync_map_loader_config<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    config: Res<MapLoaderConfig<TKeyAction, TAxisAction>>,
+    defaults: Option<Res<DefaultActionMap<TKeyAction, TAxisAction>>>,
+    migrations: Res<BindingsMigrations>,
+    settings: Res<MapIoSettings>,
+    mut had_defaults: Local<bool>,
+) {
+    // removing the defaults counts as a change too
+    let defaults_changed = defaults.as_ref().map_or(*had_defaults, Res::is_changed);
+    *had_defaults = defaults.is_some();
+    if !defaults_changed && !migrations.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let mut config = config.0.write().unwrap_or_else(PoisonError::into_inner);
+    config.defaults = defaults.map_or_else(ActionMap::default, |defaults| defaults.0.clone());
+    config.migrations = migrations.clone();
+    config.lenient = settings.lenient;
+}
+
+// copies the bindings of `Handle<ActionMap>` assets into the `ActionMap` of the same entity
+// whenever the handle changes or the asset is (re)loaded
+pub(crate) fn apply_map_assets<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<ActionMap<TKeyAction, TAxisAction>>>,
+    assets: Res<Assets<ActionMap<TKeyAction, TAxisAction>>>,
+    mut map_q: Query<(
+        Entity,
+        &Handle<ActionMap<TKeyAction, TAxisAction>>,
+        ChangeTrackers<Handle<ActionMap<TKeyAction, TAxisAction>>>,
+        Option<&mut ActionMap<TKeyAction, TAxisAction>>,
+        Option<&mut ActionInput<TKeyAction, TAxisAction>>,
+    )>,
+) {
+    let updated_assets: HashSet<_> = asset_events
+        .iter()
+        .filter_map(|ev| match ev {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+
+    for (entity, handle, handle_tracker, map, input) in map_q.iter_mut() {
+        if !handle_tracker.is_changed() && !updated_assets.contains(&handle.id) {
+            continue;
+        }
+
+        let asset = if let Some(asset) = assets.get(handle) {
+            asset
+        } else {
+            continue;
+        };
+
+        if let Some(mut map) = map {
+            if let Some(mut input) = input {
+                input.clear_rebound_actions(&map, asset);
+            }
+
+            *map = asset.clone();
+        } else {
+            commands.entity(entity).insert(asset.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_support::fixtures::{
+            input, test_map, MenuAction, TestAction, TestAxis, TestInput, TestMap,
+        },
+        versioning::BindingsFile,
+        ActionMap, AppActionInputExt, DefaultActionMap, MapAssetExtensions, MapFormat,
+    };
+    use bevy::{
+        asset::{AssetPlugin, AssetServerSettings},
+        core::CorePlugin,
+        ecs::event::Events,
+        input::{keyboard::KeyboardInput, ElementState, InputPlugin},
+        prelude::*,
+    };
+
+    type MenuMap = ActionMap<MenuAction>;
+
+    fn setup_app(asset_folder: &std::path::Path) -> App {
+        let mut app = App::new();
+        app.insert_resource(AssetServerSettings {
+            asset_folder: asset_folder.to_string_lossy().into(),
+            watch_for_changes: false,
+        })
+        .add_plugin(CorePlugin)
+        .add_plugin(AssetPlugin)
+        .add_plugin(InputPlugin)
+        .add_action_input_systems_with_axis::<TestAction, TestAxis>()
+        .add_action_serialize_systems_with_axis::<TestAction, TestAxis>();
+        app
+    }
+
+    fn load_until<T: bevy::asset::Asset>(app: &mut App, handle: &Handle<T>) {
+        for _ in 0..200 {
+            app.update();
+            if app.world.resource::<Assets<T>>().contains(handle) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("{:?} didn't load", handle);
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state: ElementState::Pressed,
+            });
+    }
+
+    fn asset_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("bevy_input_assets_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_map(dir: &std::path::Path, map: &TestMap) {
+        let file = BindingsFile::from_map(map, &TestMap::default(), 1).unwrap();
+        std::fs::write(
+            dir.join("test.bindings"),
+            MapFormat::Ron.serialize(&file).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn spawn_loaded(app: &mut App) -> (Entity, Handle<TestMap>) {
+        let handle: Handle<TestMap> = app.world.resource::<AssetServer>().load("test.bindings");
+        let e = app.world.spawn().insert(handle.clone()).id();
+
+        for _ in 0..200 {
+            app.update();
+            if app.world.get::<TestInput>(e).is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(app.world.get::<TestMap>(e).is_some());
+        (e, handle)
+    }
+
+    #[test]
+    fn load_and_reload() {
+        let dir = asset_dir("reload");
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Space.into()),
+                (TestAction::Shoot, KeyCode::LControl.into()),
+            ],
+            [],
+        );
+        write_map(&dir, &map);
+
+        let mut app = setup_app(&dir);
+        let (e, handle) = spawn_loaded(&mut app);
+
+        press(&mut app, KeyCode::Space);
+        press(&mut app, KeyCode::LControl);
+        app.update();
+        app.update();
+        assert!(input(&app, e).held(TestAction::Jump));
+        assert!(input(&app, e).held(TestAction::Shoot));
+
+        // rebind shoot only
+        let mut assets = app.world.resource_mut::<Assets<TestMap>>();
+        let asset = assets.get_mut(&handle).unwrap();
+        asset.clear_bindings();
+        asset
+            .bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::RControl)
+            .unwrap();
+        app.update();
+        app.update();
+
+        assert!(input(&app, e).held(TestAction::Jump));
+        assert!(input(&app, e)
+            .get_button_action_state(TestAction::Shoot)
+            .is_none());
+        assert!(app
+            .world
+            .get::<TestMap>(e)
+            .unwrap()
+            .get_key_bindings()
+            .values()
+            .flatten()
+            .any(|binding| binding.contains(&KeyCode::RControl.into())));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn defaults_added_after_setup() {
+        let dir = asset_dir("defaults");
+        write_map(&dir, &TestMap::default());
+
+        let mut app = setup_app(&dir);
+        let defaults = test_map([(TestAction::Shoot, KeyCode::LControl.into())], []);
+        app.insert_resource(DefaultActionMap(defaults));
+        app.update();
+        let (e, _) = spawn_loaded(&mut app);
+
+        assert!(app
+            .world
+            .get::<TestMap>(e)
+            .unwrap()
+            .get_key_bindings()
+            .contains_key(&TestAction::Shoot.into()));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn several_action_types() {
+        let dir = asset_dir("several_types");
+        write_map(
+            &dir,
+            &test_map([(TestAction::Jump, KeyCode::Space.into())], []),
+        );
+        let mut menu_map = MenuMap::default();
+        menu_map
+            .bind_button_action(MenuAction::Confirm, KeyCode::Return)
+            .unwrap();
+        let file = BindingsFile::from_map(&menu_map, &MenuMap::default(), 1).unwrap();
+        std::fs::write(
+            dir.join("test.menu.bindings"),
+            MapFormat::Ron.serialize(&file).unwrap(),
+        )
+        .unwrap();
+
+        let mut app = setup_app(&dir);
+        app.insert_resource(MapAssetExtensions::<MenuAction>::new(["menu.bindings"]))
+            .add_action_input_systems::<MenuAction>()
+            .add_action_serialize_systems::<MenuAction>();
+
+        let server = app.world.resource::<AssetServer>().clone();
+        let handle: Handle<TestMap> = server.load("test.bindings");
+        let menu_handle: Handle<MenuMap> = server.load("test.menu.bindings");
+        load_until(&mut app, &handle);
+        load_until(&mut app, &menu_handle);
+
+        assert!(app
+            .world
+            .resource::<Assets<TestMap>>()
+            .get(&handle)
+            .unwrap()
+            .get_key_bindings()
+            .contains_key(&TestAction::Jump.into()));
+        assert!(app
+            .world
+            .resource::<Assets<MenuMap>>()
+            .get(&menu_handle)
+            .unwrap()
+            .get_key_bindings()
+            .contains_key(&MenuAction::Confirm.into()));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn claimed_extensions_are_kept() {
+        let dir = asset_dir("claimed");
+        write_map(
+            &dir,
+            &test_map([(TestAction::Jump, KeyCode::Space.into())], []),
+        );
+
+        // no extension of its own, registering it doesn't take `bindings` over
+        let mut app = setup_app(&dir);
+        app.add_action_input_systems::<MenuAction>()
+            .add_action_serialize_systems::<MenuAction>();
+        assert!(app
+            .world
+            .resource::<MapAssetExtensions<MenuAction>>()
+            .extensions()
+            .is_empty());
+
+        let handle: Handle<TestMap> = app.world.resource::<AssetServer>().load("test.bindings");
+        load_until(&mut app, &handle);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{app_ext::NoAxis, ActionMapInput};
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+// ignores the device input of the `ActionInput` on the same entity, or of every entity when it's
+// inserted as a resource - muted actions are released and muted axes are zeroed, injected input
+// still goes through
+#[derive(Component, Debug, Clone)]
+pub struct InputMuted<TKeyAction, TAxisAction = NoAxis> {
+    allowed_actions: HashSet<TKeyAction>,
+    allowed_axes: HashSet<TAxisAction>,
+}
+
+impl<TKeyAction, TAxisAction> Default for InputMuted<TKeyAction, TAxisAction> {
+    fn default() -> Self {
+        Self {
+            allowed_actions: Default::default(),
+            allowed_axes: Default::default(),
+        }
+    }
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> InputMuted<TKeyAction, TAxisAction> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // keeps an action working while muted, e.g. the pause action
+    #[must_use]
+    pub fn with_allowed_action(mut self, action: TKeyAction) -> Self {
+        self.allow_action(action);
+        self
+    }
+
+    #[must_use]
+    pub fn with_allowed_axis(mut self, axis: TAxisAction) -> Self {
+        self.allow_axis(axis);
+        self
+    }
+
+    pub fn allow_action(&mut self, action: TKeyAction) {
+        self.allowed_actions.insert(action);
+    }
+
+    pub fn allow_axis(&mut self, axis: TAxisAction) {
+        self.allowed_axes.insert(axis);
+    }
+
+    pub fn is_action_allowed(&self, action: TKeyAction) -> bool {
+        self.allowed_actions.contains(&action)
+    }
+
+    pub fn is_axis_allowed(&self, axis: TAxisAction) -> bool {
+        self.allowed_axes.contains(&axis)
+    }
+}
+
+// an action is muted if the entity or the global mute doesn't allow it
+pub(crate) fn is_action_muted<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
+    mutes: &[Option<&InputMuted<TKeyAction, TAxisAction>>],
+    action: TKeyAction,
+) -> bool {
+    mutes
+        .iter()
+        .flatten()
+        .any(|mute| !mute.is_action_allowed(action))
+}
+
+pub(crate) fn is_axis_muted<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>(
+    mutes: &[Option<&InputMuted<TKeyAction, TAxisAction>>],
+    axis: TAxisAction,
+) -> bool {
+    mutes
+        .iter()
+        .flatten()
+        .any(|mute| !mute.is_axis_allowed(axis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputMuted;
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestAxis},
+        AxisBinding, InjectedInput, InjectionDuration, InputSimulationExt,
+    };
+    use bevy::prelude::*;
+    use pretty_assertions::assert_eq;
+
+    type TestMute = InputMuted<TestAction, TestAxis>;
+
+    fn setup_app() -> (App, Entity, Entity) {
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Space.into()),
+                (TestAction::Pause, KeyCode::Escape.into()),
+            ],
+            [(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )],
+        );
+
+        let mut app = test_app();
+        let e1 = spawn_player(&mut app, map.clone());
+        let e2 = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.1);
+
+        app.simulate_press(KeyCode::Space)
+            .simulate_press(KeyCode::Escape)
+            .simulate_axis(GamepadAxisType::LeftStickX, 0.5);
+        app.advance_frames(1, 0.1);
+        (app, e1, e2)
+    }
+
+    fn states(app: &App, e: Entity) -> (String, String, f32) {
+        let input = input(app, e);
+        let state = |action| {
+            input
+                .get_button_action_state(action)
+                .map_or("-".to_string(), |state| format!("{:?}", state))
+        };
+        (
+            state(TestAction::Jump),
+            state(TestAction::Pause),
+            input.get_axis(&TestAxis::Horizontal),
+        )
+    }
+
+    #[test]
+    fn muting_an_entity() {
+        let (mut app, e1, e2) = setup_app();
+
+        app.world
+            .entity_mut(e1)
+            .insert(TestMute::new().with_allowed_action(TestAction::Pause));
+        app.advance_frames(1, 0.1);
+        assert_eq!(
+            (
+                "Released: 0.10".to_string(),
+                "Pressed: 0.10".to_string(),
+                0.
+            ),
+            states(&app, e1)
+        );
+        assert_eq!(
+            (
+                "Pressed: 0.10".to_string(),
+                "Pressed: 0.10".to_string(),
+                0.5
+            ),
+            states(&app, e2)
+        );
+
+        // a button held through the mute isn't pressed again
+        app.world.entity_mut(e1).remove::<TestMute>();
+        app.advance_frames(1, 0.1);
+        assert_eq!(
+            ("-".to_string(), "Pressed: 0.20".to_string(), 0.5),
+            states(&app, e1)
+        );
+    }
+
+    #[test]
+    fn muting_globally() {
+        let (mut app, e1, e2) = setup_app();
+
+        app.insert_resource(TestMute::new().with_allowed_axis(TestAxis::Horizontal));
+        app.advance_frames(1, 0.1);
+        for e in [e1, e2] {
+            assert_eq!(
+                (
+                    "Released: 0.10".to_string(),
+                    "Released: 0.10".to_string(),
+                    0.5
+                ),
+                states(&app, e)
+            );
+        }
+
+        app.world.remove_resource::<TestMute>();
+        app.simulate_release(KeyCode::Space);
+        app.advance_frames(1, 0.1);
+        app.simulate_press(KeyCode::Space);
+        app.advance_frames(1, 0.1);
+        assert_eq!(
+            ("Pressed".to_string(), "-".to_string(), 0.5),
+            states(&app, e1)
+        );
+    }
+
+    #[test]
+    fn injected_input_goes_through() {
+        let (mut app, e1, _) = setup_app();
+
+        let mut injected = InjectedInput::<TestAction, TestAxis>::default();
+        injected.press(TestAction::Jump, InjectionDuration::UntilCleared);
+        app.world
+            .entity_mut(e1)
+            .insert(TestMute::new())
+            .insert(injected);
+        app.advance_frames(1, 0.1);
+
+        assert_eq!(
+            (
+                "Pressed: 0.10".to_string(),
+                "Released: 0.10".to_string(),
+                0.
+            ),
+            states(&app, e1)
+        );
+    }
+}
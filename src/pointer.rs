@@ -0,0 +1,286 @@
+use crate::{ActionInput, ActionMapInput, ActiveDeviceSettings, InputDevice, InputGamepad};
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    prelude::*,
+    window::WindowId,
+};
+
+// cursor position is in the window space (origin in the bottom left corner)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pointer {
+    window: Option<WindowId>,
+    position: Option<Vec2>,
+    delta: Vec2,
+    world_position: Option<Vec2>,
+}
+
+impl Pointer {
+    pub fn position(&self) -> Option<Vec2> {
+        self.position
+    }
+
+    pub fn delta(&self) -> Vec2 {
+        self.delta
+    }
+
+    // only available when the entity has a `PointerCamera`
+    pub fn world_position(&self) -> Option<Vec2> {
+        self.world_position
+    }
+}
+
+// camera used to project the pointer of the `ActionInput` on the same entity into the world space
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PointerCamera(pub Entity);
+
+// unprojects through the camera's projection, so zoomed (`OrthographicProjection::scale`) cameras
+// and other window origins or scaling modes are handled
+pub fn screen_to_world_2d(
+    screen_position: Vec2,
+    window_size: Vec2,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Vec2 {
+    let ndc = screen_position / window_size * 2. - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    ndc_to_world.project_point3(ndc.extend(-1.)).truncate()
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionInput<TKeyAction, TAxisAction> {
+    pub fn pointer(&self) -> &Pointer {
+        &self.pointer
+    }
+
+    // direction from the origin to the pointer, or the axes direction when a gamepad or touch was
+    // used last - keys pressed while aiming with the mouse don't switch away from the pointer
+    pub fn aim_direction(&self, origin: Vec2, x_axis: &TAxisAction, y_axis: &TAxisAction) -> Vec2 {
+        match (self.last_device, self.pointer.world_position) {
+            (Some(InputDevice::Gamepad(_) | InputDevice::Touch), _) | (_, None) => {
+                self.get_xy_axes(x_axis, y_axis)
+            }
+            (_, Some(world_position)) => (world_position - origin).normalize_or_zero(),
+        }
+    }
+}
+
+pub(crate) fn handle_pointer_input<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(
+        &mut ActionInput<TKeyAction, TAxisAction>,
+        Option<&PointerCamera>,
+        Option<&InputGamepad>,
+    )>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    cursor_events: Option<Res<Events<CursorMoved>>>,
+    mut cursor_reader: Local<ManualEventReader<CursorMoved>>,
+    windows: Option<Res<Windows>>,
+    device_settings: Res<ActiveDeviceSettings>,
+) {
+    let last_cursor_event = cursor_events
+        .as_ref()
+        .and_then(|events| cursor_reader.iter(events).last().cloned());
+
+    for (mut input, camera, pad) in input_q.iter_mut() {
+        let pointer = &mut input.pointer;
+        pointer.delta = Vec2::ZERO;
+
+        if let Some(ev) = &last_cursor_event {
+            if let Some(prev_position) = pointer.position {
+                pointer.delta = ev.position - prev_position;
+            }
+
+            pointer.window = Some(ev.id);
+            pointer.position = Some(ev.position);
+        }
+
+        pointer.world_position = match (camera, pointer.position, pointer.window, &windows) {
+            (Some(camera), Some(position), Some(window), Some(windows)) => {
+                match (camera_q.get(camera.0), windows.get(window)) {
+                    (Ok((camera, camera_transform)), Some(window)) => Some(screen_to_world_2d(
+                        position,
+                        Vec2::new(window.width(), window.height()),
+                        camera,
+                        camera_transform,
+                    )),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        // players locked to a gamepad don't own the mouse
+        if pad.is_none() && input.pointer.delta.length() > device_settings.cursor_noise_threshold {
+            input.last_device = Some(InputDevice::Mouse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::screen_to_world_2d;
+    use crate::{
+        test_support::fixtures::{
+            spawn_player, test_app, test_map, TestAction, TestAxis, TestInput,
+        },
+        AxisBinding, InputDevice,
+    };
+    use bevy::{
+        ecs::event::Events,
+        input::{
+            gamepad::{GamepadEvent, GamepadEventType},
+            keyboard::KeyboardInput,
+            ElementState,
+        },
+        prelude::*,
+        render::camera::{CameraProjection, WindowOrigin},
+        window::WindowId,
+    };
+    use test_case::test_case;
+
+    // aims with the right stick
+    fn setup_app() -> (App, Entity) {
+        let map = test_map(
+            [(TestAction::Jump, KeyCode::Space.into())],
+            [
+                (
+                    TestAxis::Horizontal,
+                    AxisBinding::GamepadAxis(GamepadAxisType::RightStickX),
+                ),
+                (
+                    TestAxis::Vertical,
+                    AxisBinding::GamepadAxis(GamepadAxisType::RightStickY),
+                ),
+            ],
+        );
+
+        let mut app = test_app();
+        app.add_event::<CursorMoved>();
+        let e = spawn_player(&mut app, map);
+        app.update();
+        (app, e)
+    }
+
+    fn move_cursor(app: &mut App, x: f32, y: f32) {
+        app.world
+            .resource_mut::<Events<CursorMoved>>()
+            .send(CursorMoved {
+                id: WindowId::primary(),
+                position: Vec2::new(x, y),
+            });
+    }
+
+    #[test]
+    fn cursor_position_and_delta() {
+        let (mut app, e) = setup_app();
+
+        move_cursor(&mut app, 10., 20.);
+        app.update();
+        let input = app.world.get::<TestInput>(e).unwrap();
+        assert_eq!(Some(Vec2::new(10., 20.)), input.pointer().position());
+        assert_eq!(Vec2::ZERO, input.pointer().delta());
+        // the cursor entering the window isn't movement
+        assert_eq!(None, input.last_device());
+
+        move_cursor(&mut app, 11., 21.);
+        app.update();
+        assert_eq!(None, app.world.get::<TestInput>(e).unwrap().last_device());
+
+        move_cursor(&mut app, 16., 11.);
+        app.update();
+        let input = app.world.get::<TestInput>(e).unwrap();
+        assert_eq!(Vec2::new(5., -10.), input.pointer().delta());
+        assert_eq!(Some(InputDevice::Mouse), input.last_device());
+
+        app.world
+            .resource_mut::<Events<GamepadEvent>>()
+            .send(GamepadEvent(
+                Gamepad(0),
+                GamepadEventType::AxisChanged(GamepadAxisType::RightStickX, 1.),
+            ));
+        app.update();
+        let input = app.world.get::<TestInput>(e).unwrap();
+        assert_eq!(Vec2::ZERO, input.pointer().delta());
+        assert_eq!(Some(InputDevice::Gamepad(0)), input.last_device());
+        assert_eq!(
+            Vec2::X,
+            input.aim_direction(Vec2::ZERO, &TestAxis::Horizontal, &TestAxis::Vertical)
+        );
+    }
+
+    #[test]
+    fn keys_pressed_while_aiming_with_the_mouse() {
+        let (mut app, e) = setup_app();
+
+        move_cursor(&mut app, 10., 20.);
+        app.update();
+        move_cursor(&mut app, 20., 20.);
+        app.update();
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::Space),
+                state: ElementState::Pressed,
+            });
+        app.update();
+
+        let mut input = app.world.get_mut::<TestInput>(e).unwrap();
+        assert_eq!(Some(InputDevice::Keyboard), input.last_device());
+        // no window to project the cursor with in a headless app
+        input.pointer.world_position = Some(Vec2::new(0., 5.));
+        assert_eq!(
+            Vec2::Y,
+            input.aim_direction(Vec2::ZERO, &TestAxis::Horizontal, &TestAxis::Vertical)
+        );
+    }
+
+    fn camera(projection: OrthographicProjection) -> Camera {
+        let mut projection = projection;
+        projection.update(800., 600.);
+        Camera {
+            projection_matrix: projection.get_projection_matrix(),
+            ..Camera::default()
+        }
+    }
+
+    #[test_case(1., Vec3::ONE => Vec2::new(110., 40.); "default")]
+    #[test_case(1., Vec3::splat(2.) => Vec2::new(120., 30.); "scaled transform")]
+    #[test_case(0.5, Vec3::ONE => Vec2::new(105., 45.); "zoomed in")]
+    #[test_case(3., Vec3::ONE => Vec2::new(130., 20.); "zoomed out")]
+    fn project_to_world(scale: f32, transform_scale: Vec3) -> Vec2 {
+        let camera_transform =
+            GlobalTransform::from_xyz(100., 50., 999.).with_scale(transform_scale);
+        let camera = camera(OrthographicProjection {
+            scale,
+            ..OrthographicProjection::default()
+        });
+        let world = screen_to_world_2d(
+            Vec2::new(410., 290.),
+            Vec2::new(800., 600.),
+            &camera,
+            &camera_transform,
+        );
+        (world * 1000.).round() / 1000.
+    }
+
+    #[test]
+    fn project_with_bottom_left_origin() {
+        let camera = camera(OrthographicProjection {
+            window_origin: WindowOrigin::BottomLeft,
+            scale: 2.,
+            ..OrthographicProjection::default()
+        });
+        assert_eq!(
+            Vec2::new(820., 580.),
+            screen_to_world_2d(
+                Vec2::new(410., 290.),
+                Vec2::new(800., 600.),
+                &camera,
+                &GlobalTransform::identity(),
+            )
+            .round()
+        );
+    }
+}
@@ -0,0 +1,659 @@
+use crate::{
+    location::write_atomic, ActionInput, ActionMap, BindingsLocation, BindingsPaths, MapFormat,
+    MapIoError, MapIoRequest, MapIoRequestId, MapIoSettings, SerializableActionInput,
+};
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// a named bindings profile, the entity also holds the profile's `ActionMap`
+// and doesn't process any input itself
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct BindingsProfile {
+    name: String,
+    slot: usize,
+}
+
+impl BindingsProfile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+}
+
+// marks a player entity, its `ActionMap` follows the profile assigned to the player id
+// regardless of the controller slot the player uses
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProfilePlayer(pub usize);
+
+#[derive(Debug, Clone)]
+pub enum ProfileRequest {
+    // creates the profile or replaces its bindings with the current bindings of `source`
+    Store { name: String, source: Entity },
+    Delete(String),
+    Assign { player: usize, name: String },
+    Unassign(usize),
+    // writes the profile index, then saves every profile to its slot file
+    Save,
+    // replaces all profiles and assignments with the ones in the profile index
+    Load,
+}
+
+// slot files are read and written through `MapIoRequest`s, the `MapIoEvent`s
+// with the returned ids report when they're done
+#[derive(Debug, Clone)]
+pub enum ProfileEvent {
+    Saved { slot_requests: Vec<MapIoRequestId> },
+    Loaded { slot_requests: Vec<MapIoRequestId> },
+    SaveFailed(MapIoError),
+    LoadFailed(MapIoError),
+    StoreFailed { name: String, error: MapIoError },
+    UnknownProfile(String),
+}
+
+// the names and slots of all profiles and the profile assigned to each player, profiles hold the
+// `ActionMap`s of the first action types added with `add_action_serialize_systems`
+pub struct BindingsProfiles {
+    directory: BindingsLocation,
+    format: MapFormat,
+    slots: BTreeMap<String, usize>,
+    assignments: BTreeMap<usize, String>,
+    entities: HashMap<String, Entity>,
+}
+
+impl Default for BindingsProfiles {
+    fn default() -> Self {
+        Self::new(BindingsLocation::user_config("profiles"))
+    }
+}
+
+impl BindingsProfiles {
+    // the profile index and slot files are stored in `directory`
+    pub fn new(directory: impl Into<BindingsLocation>) -> Self {
+        Self {
+            directory: directory.into(),
+            format: MapFormat::default(),
+            slots: BTreeMap::new(),
+            assignments: BTreeMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_format(mut self, format: MapFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = &str> {
+        self.slots.keys().map(String::as_str)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<Entity> {
+        self.entities.get(name).copied()
+    }
+
+    pub fn assigned(&self, player: usize) -> Option<&str> {
+        self.assignments.get(&player).map(String::as_str)
+    }
+
+    pub fn index_location(&self) -> BindingsLocation {
+        // `.bindings` files are action maps, the index is plain RON
+        let extension = match self.format {
+            MapFormat::Ron => "ron",
+            format => format.extension(),
+        };
+        self.directory.join(format!("profiles.{}", extension))
+    }
+
+    pub fn slot_location(&self, slot: usize) -> BindingsLocation {
+        self.directory
+            .join(format!("slot_{}.{}", slot, self.format.extension()))
+    }
+
+    fn free_slot(&self) -> usize {
+        (0..=self.slots.len())
+            .find(|slot| !self.slots.values().any(|used| used == slot))
+            .unwrap_or_default()
+    }
+
+    fn index(&self) -> ProfileIndex {
+        ProfileIndex {
+            profiles: self
+                .slots
+                .iter()
+                .map(|(name, slot)| ProfileSlot {
+                    name: name.clone(),
+                    slot: *slot,
+                })
+                .collect(),
+            players: self
+                .assignments
+                .iter()
+                .map(|(player, profile)| PlayerProfile {
+                    player: *player,
+                    profile: profile.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileSlot {
+    name: String,
+    slot: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PlayerProfile {
+    player: usize,
+    profile: String,
+}
+
+// the profile index file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileIndex {
+    #[serde(default)]
+    profiles: Vec<ProfileSlot>,
+    #[serde(default)]
+    players: Vec<PlayerProfile>,
+}
+
+enum IndexTask {
+    Load(Task<Result<ProfileIndex, MapIoError>>),
+    Save(Task<Result<(), MapIoError>>),
+}
+
+// the action types profiles are processed for, the resources and events aren't generic so there
+// can only be one
+pub(crate) struct ProfileActions(pub(crate) &'static str);
+
+// requests wait until the index task in flight is done so they apply in order
+#[derive(Default)]
+pub(crate) struct ProfileQueue {
+    queue: VecDeque<ProfileRequest>,
+    in_flight: Option<IndexTask>,
+}
+
+pub(crate) fn process_profile_requests<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    mut commands: Commands,
+    mut event_r: EventReader<ProfileRequest>,
+    mut event_w: EventWriter<ProfileEvent>,
+    mut io_w: EventWriter<MapIoRequest>,
+    mut profile_queue: ResMut<ProfileQueue>,
+    mut profiles: ResMut<BindingsProfiles>,
+    map_q: Query<&ActionMap<TKeyAction, TAxisAction>>,
+    thread_pool: Res<AsyncComputeTaskPool>,
+    paths: Res<BindingsPaths>,
+    settings: Res<MapIoSettings>,
+) {
+    profile_queue.queue.extend(event_r.iter().cloned());
+
+    loop {
+        if let Some(mut task) = profile_queue.in_flight.take() {
+            match &mut task {
+                IndexTask::Load(load) => match future::block_on(future::poll_once(load)) {
+                    Some(Ok(index)) => {
+                        let slot_requests = load_profiles::<TKeyAction, TAxisAction>(
+                            &mut commands,
+                            &mut io_w,
+                            &mut profiles,
+                            index,
+                        );
+                        event_w.send(ProfileEvent::Loaded { slot_requests });
+                    }
+                    Some(Err(error)) => event_w.send(ProfileEvent::LoadFailed(error)),
+                    None => {
+                        profile_queue.in_flight = Some(task);
+                        return;
+                    }
+                },
+                IndexTask::Save(save) => match future::block_on(future::poll_once(save)) {
+                    Some(Ok(())) => {
+                        let slot_requests = save_profiles(&mut io_w, &profiles);
+                        event_w.send(ProfileEvent::Saved { slot_requests });
+                    }
+                    Some(Err(error)) => event_w.send(ProfileEvent::SaveFailed(error)),
+                    None => {
+                        profile_queue.in_flight = Some(task);
+                        return;
+                    }
+                },
+            }
+        }
+
+        let request = if let Some(request) = profile_queue.queue.pop_front() {
+            request
+        } else {
+            return;
+        };
+
+        match request {
+            ProfileRequest::Store { name, source } => match map_q.get(source) {
+                Ok(map) => store_profile(&mut commands, &mut profiles, name, map.clone()),
+                Err(_) => event_w.send(ProfileEvent::StoreFailed {
+                    name,
+                    error: MapIoError::MissingMap,
+                }),
+            },
+            ProfileRequest::Delete(name) => {
+                if let Some(entity) = profiles.entities.remove(&name) {
+                    commands.entity(entity).despawn();
+                    profiles.slots.remove(&name);
+                    profiles.assignments.retain(|_, profile| *profile != name);
+                } else {
+                    event_w.send(ProfileEvent::UnknownProfile(name));
+                }
+            }
+            ProfileRequest::Assign { player, name } => {
+                if profiles.slots.contains_key(&name) {
+                    profiles.assignments.insert(player, name);
+                } else {
+                    event_w.send(ProfileEvent::UnknownProfile(name));
+                }
+            }
+            ProfileRequest::Unassign(player) => {
+                profiles.assignments.remove(&player);
+            }
+            ProfileRequest::Save => match paths.resolve(&profiles.index_location()) {
+                Ok(path) => {
+                    let (index, format, backup) =
+                        (profiles.index(), profiles.format, settings.backup);
+                    profile_queue.in_flight =
+                        Some(IndexTask::Save(thread_pool.spawn(async move {
+                            let bytes = format.serialize(&index)?;
+                            write_atomic(path, bytes, backup).await
+                        })));
+                }
+                Err(error) => event_w.send(ProfileEvent::SaveFailed(error)),
+            },
+            ProfileRequest::Load => match paths.resolve(&profiles.index_location()) {
+                Ok(path) => {
+                    let format = profiles.format;
+                    profile_queue.in_flight =
+                        Some(IndexTask::Load(thread_pool.spawn(async move {
+                            let bytes = async_fs::read(path).await?;
+                            format.deserialize(&bytes)
+                        })));
+                }
+                Err(error) => event_w.send(ProfileEvent::LoadFailed(error)),
+            },
+        }
+    }
+}
+
+fn store_profile<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    commands: &mut Commands,
+    profiles: &mut BindingsProfiles,
+    name: String,
+    map: ActionMap<TKeyAction, TAxisAction>,
+) {
+    if let Some(entity) = profiles.profile(&name) {
+        commands.entity(entity).insert(map);
+    } else {
+        let slot = profiles.free_slot();
+        let entity = spawn_profile(commands, name.clone(), slot, map);
+        profiles.slots.insert(name.clone(), slot);
+        profiles.entities.insert(name, entity);
+    }
+}
+
+fn spawn_profile<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    commands: &mut Commands,
+    name: String,
+    slot: usize,
+    map: ActionMap<TKeyAction, TAxisAction>,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(BindingsProfile { name, slot })
+        .insert(map)
+        .id()
+}
+
+fn save_profiles(
+    io_w: &mut EventWriter<MapIoRequest>,
+    profiles: &BindingsProfiles,
+) -> Vec<MapIoRequestId> {
+    profiles
+        .slots
+        .iter()
+        .filter_map(|(name, slot)| {
+            let request =
+                MapIoRequest::save(profiles.profile(name)?, profiles.slot_location(*slot))
+                    .with_format(profiles.format);
+            let id = request.id();
+            io_w.send(request);
+            Some(id)
+        })
+        .collect()
+}
+
+// replaces the current profiles with empty ones and loads their bindings from the slot files
+fn load_profiles<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    commands: &mut Commands,
+    io_w: &mut EventWriter<MapIoRequest>,
+    profiles: &mut BindingsProfiles,
+    index: ProfileIndex,
+) -> Vec<MapIoRequestId> {
+    for (_, entity) in profiles.entities.drain() {
+        commands.entity(entity).despawn();
+    }
+
+    profiles.slots.clear();
+    let mut slot_requests = Vec::new();
+    for ProfileSlot { name, slot } in index.profiles {
+        let entity = spawn_profile(
+            commands,
+            name.clone(),
+            slot,
+            ActionMap::<TKeyAction, TAxisAction>::default(),
+        );
+        let request =
+            MapIoRequest::load(entity, profiles.slot_location(slot)).with_format(profiles.format);
+        slot_requests.push(request.id());
+        io_w.send(request);
+        profiles.slots.insert(name.clone(), slot);
+        profiles.entities.insert(name, entity);
+    }
+
+    profiles.assignments = index
+        .players
+        .into_iter()
+        .filter(|player| profiles.slots.contains_key(&player.profile))
+        .map(|player| (player.player, player.profile))
+        .collect();
+
+    slot_requests
+}
+
+// copies the bindings of the assigned profiles into the maps of the players
+pub(crate) fn apply_profiles<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    profiles: Res<BindingsProfiles>,
+    profile_q: Query<
+        (
+            &ActionMap<TKeyAction, TAxisAction>,
+            ChangeTrackers<ActionMap<TKeyAction, TAxisAction>>,
+        ),
+        With<BindingsProfile>,
+    >,
+    mut player_q: Query<
+        (
+            &ProfilePlayer,
+            ChangeTrackers<ProfilePlayer>,
+            &mut ActionMap<TKeyAction, TAxisAction>,
+            Option<&mut ActionInput<TKeyAction, TAxisAction>>,
+        ),
+        Without<BindingsProfile>,
+    >,
+) {
+    for (player, player_tracker, mut map, input) in player_q.iter_mut() {
+        let profile = profiles
+            .assigned(player.0)
+            .and_then(|name| profiles.profile(name))
+            .and_then(|entity| profile_q.get(entity).ok());
+
+        if let Some((profile_map, profile_tracker)) = profile {
+            if profiles.is_changed() || player_tracker.is_changed() || profile_tracker.is_changed()
+            {
+                if let Some(mut input) = input {
+                    input.clear_rebound_actions(&map, profile_map);
+                }
+                *map = profile_map.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindingsProfiles, ProfileEvent, ProfilePlayer, ProfileRequest};
+    use crate::{
+        test_support::fixtures::{test_app, test_map, MenuAction, TestAction, TestAxis, TestMap},
+        ActionMap, AppActionInputExt, MapIoEvent, MapIoRequestId,
+    };
+    use bevy::{ecs::event::Events, prelude::*};
+
+    fn setup_app(profiles: BindingsProfiles) -> App {
+        let mut app = test_app();
+        app.insert_resource(profiles)
+            .add_action_serialize_systems_with_axis::<TestAction, TestAxis>();
+        app
+    }
+
+    fn jump_map(key: KeyCode) -> TestMap {
+        test_map([(TestAction::Jump, key.into())], [])
+    }
+
+    fn send(app: &mut App, request: ProfileRequest) {
+        app.world
+            .resource_mut::<Events<ProfileRequest>>()
+            .send(request);
+    }
+
+    fn is_bound(app: &App, e: Entity, key: KeyCode) -> bool {
+        app.world.get::<TestMap>(e).unwrap().get_key_bindings()[&TestAction::Jump.into()]
+            .iter()
+            .any(|binding| binding.contains(&key.into()))
+    }
+
+    // waits for the profile request and the map io requests it started
+    fn wait_for(app: &mut App) -> ProfileEvent {
+        // events of earlier requests may still be buffered
+        let mut profile_reader = app
+            .world
+            .resource::<Events<ProfileEvent>>()
+            .get_reader_current();
+        let mut io_reader = app
+            .world
+            .resource::<Events<MapIoEvent>>()
+            .get_reader_current();
+        let mut result = None;
+        let mut pending: Vec<MapIoRequestId> = Vec::new();
+
+        for _ in 0..200 {
+            app.update();
+            let events = app.world.resource::<Events<ProfileEvent>>();
+            if let Some(ev) = profile_reader.iter(events).next() {
+                if let ProfileEvent::Saved { slot_requests }
+                | ProfileEvent::Loaded { slot_requests } = ev
+                {
+                    pending = slot_requests.clone();
+                }
+                result = Some(ev.clone());
+            }
+
+            let events = app.world.resource::<Events<MapIoEvent>>();
+            for ev in io_reader.iter(events) {
+                assert!(
+                    matches!(ev, MapIoEvent::Saved { .. } | MapIoEvent::Loaded { .. }),
+                    "{:?}",
+                    ev
+                );
+                pending.retain(|id| *id != ev.id());
+            }
+
+            if let (Some(ev), true) = (&result, pending.is_empty()) {
+                return ev.clone();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        panic!("profile request timed out");
+    }
+
+    #[test]
+    fn assigned_profile_follows_player() {
+        let mut app = setup_app(BindingsProfiles::default());
+        let source = app.world.spawn().insert(jump_map(KeyCode::Space)).id();
+        let player = app
+            .world
+            .spawn()
+            .insert(TestMap::default())
+            .insert(ProfilePlayer(0))
+            .id();
+        let other = app
+            .world
+            .spawn()
+            .insert(jump_map(KeyCode::Up))
+            .insert(ProfilePlayer(1))
+            .id();
+
+        send(
+            &mut app,
+            ProfileRequest::Store {
+                name: "alice".to_string(),
+                source,
+            },
+        );
+        send(
+            &mut app,
+            ProfileRequest::Assign {
+                player: 0,
+                name: "alice".to_string(),
+            },
+        );
+        app.update();
+        app.update();
+        assert!(is_bound(&app, player, KeyCode::Space));
+        assert!(is_bound(&app, other, KeyCode::Up));
+
+        // updating the profile updates its players
+        *app.world.get_mut::<TestMap>(source).unwrap() = jump_map(KeyCode::W);
+        send(
+            &mut app,
+            ProfileRequest::Store {
+                name: "alice".to_string(),
+                source,
+            },
+        );
+        app.update();
+        app.update();
+        assert!(is_bound(&app, player, KeyCode::W));
+
+        // the profile moves with the player to another slot
+        app.world.get_mut::<ProfilePlayer>(player).unwrap().0 = 1;
+        send(&mut app, ProfileRequest::Unassign(0));
+        send(
+            &mut app,
+            ProfileRequest::Assign {
+                player: 1,
+                name: "alice".to_string(),
+            },
+        );
+        app.update();
+        app.update();
+        assert!(is_bound(&app, player, KeyCode::W));
+        assert!(is_bound(&app, other, KeyCode::W));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("bevy_input_{}_profiles", std::process::id()));
+        let mut app = setup_app(BindingsProfiles::new(dir.clone()));
+        for (name, key) in [("alice", KeyCode::Space), ("bob", KeyCode::Up)] {
+            let source = app.world.spawn().insert(jump_map(key)).id();
+            send(
+                &mut app,
+                ProfileRequest::Store {
+                    name: name.to_string(),
+                    source,
+                },
+            );
+        }
+        send(
+            &mut app,
+            ProfileRequest::Assign {
+                player: 1,
+                name: "bob".to_string(),
+            },
+        );
+        send(&mut app, ProfileRequest::Save);
+        assert!(
+            matches!(wait_for(&mut app), ProfileEvent::Saved { slot_requests } if slot_requests.len() == 2)
+        );
+
+        let mut app = setup_app(BindingsProfiles::new(dir.clone()));
+        let player = app
+            .world
+            .spawn()
+            .insert(TestMap::default())
+            .insert(ProfilePlayer(1))
+            .id();
+        send(&mut app, ProfileRequest::Load);
+        assert!(matches!(wait_for(&mut app), ProfileEvent::Loaded { .. }));
+        app.update();
+
+        let profiles = app.world.resource::<BindingsProfiles>();
+        assert_eq!(
+            vec!["alice", "bob"],
+            profiles.profiles().collect::<Vec<_>>()
+        );
+        assert_eq!(Some("bob"), profiles.assigned(1));
+        let alice = profiles.profile("alice").unwrap();
+        assert!(is_bound(&app, alice, KeyCode::Space));
+        assert!(is_bound(&app, player, KeyCode::Up));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn profiles_keep_the_first_action_types() {
+        let dir =
+            std::env::temp_dir().join(format!("bevy_input_{}_profiles_types", std::process::id()));
+        let mut app = setup_app(BindingsProfiles::new(dir.clone()));
+        app.add_action_input_systems::<MenuAction>()
+            .add_action_serialize_systems::<MenuAction>();
+        let source = app.world.spawn().insert(jump_map(KeyCode::Space)).id();
+
+        // stored and saved once, without failures from the menu actions
+        send(
+            &mut app,
+            ProfileRequest::Store {
+                name: "alice".to_string(),
+                source,
+            },
+        );
+        send(&mut app, ProfileRequest::Save);
+        assert!(
+            matches!(wait_for(&mut app), ProfileEvent::Saved { slot_requests } if slot_requests.len() == 1)
+        );
+
+        send(&mut app, ProfileRequest::Load);
+        assert!(
+            matches!(wait_for(&mut app), ProfileEvent::Loaded { slot_requests } if slot_requests.len() == 1)
+        );
+        let alice = app
+            .world
+            .resource::<BindingsProfiles>()
+            .profile("alice")
+            .unwrap();
+        assert!(is_bound(&app, alice, KeyCode::Space));
+        assert!(app.world.get::<ActionMap<MenuAction>>(alice).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
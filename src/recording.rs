@@ -0,0 +1,398 @@
+use crate::{
+    action_map::PlayerData, app_ext::NoAxis, input_time::InputTime, location::write_atomic,
+    ActionInput, ActionState, BindingsLocation, BindingsPaths, MapFormat, MapIoError,
+    SerializableActionInput,
+};
+use bevy::prelude::*;
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+
+// the resolved input of a single frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput")]
+pub struct RecordedFrame<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput = NoAxis,
+> {
+    // the delta the action durations of this frame were advanced by
+    pub delta_seconds: f32,
+    #[serde(default)]
+    buttons: Vec<(PlayerData<TKeyAction>, ActionState)>,
+    #[serde(default)]
+    axes: Vec<(PlayerData<TAxisAction>, f32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput")]
+pub struct InputRecording<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput = NoAxis,
+> {
+    frames: Vec<RecordedFrame<TKeyAction, TAxisAction>>,
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> Default
+    for InputRecording<TKeyAction, TAxisAction>
+{
+    fn default() -> Self {
+        Self { frames: Vec::new() }
+    }
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput>
+    InputRecording<TKeyAction, TAxisAction>
+{
+    pub fn frames(&self) -> &[RecordedFrame<TKeyAction, TAxisAction>] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// # Errors
+    ///
+    /// Will return an `Err` if the recording can't be represented in the format
+    pub fn to_bytes(&self, format: MapFormat) -> Result<Vec<u8>, MapIoError> {
+        format.serialize(self)
+    }
+
+    /// # Errors
+    ///
+    /// Will return an `Err` if the bytes aren't a recording in the format
+    pub fn from_bytes(format: MapFormat, bytes: &[u8]) -> Result<Self, MapIoError> {
+        format.deserialize(bytes)
+    }
+
+    // the format is picked by the extension like for bindings files, the file is replaced atomically
+    // without keeping the previous recording
+    /// # Errors
+    ///
+    /// Will return an `Err` if the location can't be resolved or the file can't be written
+    pub fn save(
+        &self,
+        paths: &BindingsPaths,
+        location: impl Into<BindingsLocation>,
+    ) -> Result<(), MapIoError> {
+        let location = location.into();
+        let bytes = self.to_bytes(MapFormat::from_path(location.path()))?;
+        future::block_on(write_atomic(paths.resolve(&location)?, bytes, false))
+    }
+
+    /// # Errors
+    ///
+    /// Will return an `Err` if the location can't be resolved, the file can't be read or isn't a
+    /// recording
+    pub fn load(
+        paths: &BindingsPaths,
+        location: impl Into<BindingsLocation>,
+    ) -> Result<Self, MapIoError> {
+        let location = location.into();
+        let bytes = future::block_on(async_fs::read(paths.resolve(&location)?))?;
+        Self::from_bytes(MapFormat::from_path(location.path()), &bytes)
+    }
+}
+
+// records the `ActionInput` of the same entity every frame
+#[derive(Component, Debug, Clone)]
+pub struct InputRecorder<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput = NoAxis,
+> {
+    recording: InputRecording<TKeyAction, TAxisAction>,
+    paused: bool,
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput> Default
+    for InputRecorder<TKeyAction, TAxisAction>
+{
+    fn default() -> Self {
+        Self {
+            recording: Default::default(),
+            paused: false,
+        }
+    }
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput>
+    InputRecorder<TKeyAction, TAxisAction>
+{
+    pub fn recording(&self) -> &InputRecording<TKeyAction, TAxisAction> {
+        &self.recording
+    }
+
+    // hands out the frames recorded so far and starts over
+    pub fn take_recording(&mut self) -> InputRecording<TKeyAction, TAxisAction> {
+        std::mem::take(&mut self.recording)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+}
+
+// drives the `ActionInput` of the same entity from a recording instead of the devices
+#[derive(Component, Debug, Clone)]
+pub struct InputReplay<
+    TKeyAction: SerializableActionInput,
+    TAxisAction: SerializableActionInput = NoAxis,
+> {
+    recording: InputRecording<TKeyAction, TAxisAction>,
+    next_frame: usize,
+    looping: bool,
+}
+
+impl<TKeyAction: SerializableActionInput, TAxisAction: SerializableActionInput>
+    InputReplay<TKeyAction, TAxisAction>
+{
+    pub fn new(recording: InputRecording<TKeyAction, TAxisAction>) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+            looping: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    // the number of frames replayed since the start, or since the replay last started over when
+    // looping
+    pub fn frame(&self) -> usize {
+        self.next_frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.next_frame >= self.recording.len()
+    }
+
+    // the recorded delta of the frame replayed last, use it to step the simulation deterministically
+    pub fn delta_seconds(&self) -> f32 {
+        self.next_frame
+            .checked_sub(1)
+            .and_then(|i| self.recording.frames.get(i))
+            .map_or(0., |frame| frame.delta_seconds)
+    }
+
+    fn advance(&mut self) -> Option<&RecordedFrame<TKeyAction, TAxisAction>> {
+        if self.looping && self.next_frame >= self.recording.len() {
+            self.next_frame = 0;
+        }
+
+        let frame = self.recording.frames.get(self.next_frame)?;
+        self.next_frame += 1;
+        Some(frame)
+    }
+}
+
+pub(crate) fn record_input<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    mut input_q: Query<(
+        &ActionInput<TKeyAction, TAxisAction>,
+        &mut InputRecorder<TKeyAction, TAxisAction>,
+    )>,
+    time: Res<InputTime>,
+) {
+    for (input, mut recorder) in input_q.iter_mut() {
+        if recorder.paused {
+            continue;
+        }
+
+        recorder.recording.frames.push(RecordedFrame {
+            delta_seconds: time.delta_seconds(),
+            buttons: input
+                .button_actions
+                .iter()
+                .map(|(action, state)| (*action, state.clone()))
+                .collect(),
+            axes: input
+                .axes
+                .iter()
+                .map(|(axis, value)| (*axis, *value))
+                .collect(),
+        });
+    }
+}
+
+pub(crate) fn replay_input<
+    TKeyAction: SerializableActionInput + 'static,
+    TAxisAction: SerializableActionInput + 'static,
+>(
+    mut input_q: Query<(
+        &mut ActionInput<TKeyAction, TAxisAction>,
+        &mut InputReplay<TKeyAction, TAxisAction>,
+    )>,
+) {
+    for (mut input, mut replay) in input_q.iter_mut() {
+        // nothing is active once the replay has finished
+        input.button_actions.clear();
+        // only the states are recorded, so the press timestamps aren't known
+        input.press_timestamps.clear();
+        input.axes.clear();
+
+        if let Some(frame) = replay.advance() {
+            input.button_actions.extend(frame.buttons.iter().cloned());
+            input.axes.extend(frame.axes.iter().copied());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputRecorder, InputRecording, InputReplay};
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, test_map, TestAction, TestAxis},
+        ActionState, AppActionInputExt, AxisBinding, BindingsLocation, BindingsPaths,
+        InputSimulationExt, MapFormat,
+    };
+    use bevy::prelude::*;
+    use test_case::test_case;
+
+    type TestRecorder = InputRecorder<TestAction, TestAxis>;
+    type TestRecording = InputRecording<TestAction, TestAxis>;
+
+    fn setup_app() -> (App, Entity) {
+        let map = test_map(
+            [
+                (TestAction::Jump, KeyCode::Space.into()),
+                (TestAction::Shoot, MouseButton::Left.into()),
+            ],
+            [(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )],
+        );
+
+        let mut app = test_app();
+        app.add_action_serialize_systems_with_axis::<TestAction, TestAxis>();
+        let e = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.1);
+        (app, e)
+    }
+
+    fn states(app: &App, e: Entity) -> (Option<ActionState>, Option<ActionState>, f32) {
+        let input = input(app, e);
+        (
+            input.get_button_action_state(TestAction::Jump).cloned(),
+            input.get_button_action_state(TestAction::Shoot).cloned(),
+            input.get_axis(&TestAxis::Horizontal),
+        )
+    }
+
+    // records a few frames of input along with the states they resolved to
+    fn record() -> (
+        TestRecording,
+        Vec<(Option<ActionState>, Option<ActionState>, f32)>,
+    ) {
+        let (mut app, e) = setup_app();
+        app.world.entity_mut(e).insert(TestRecorder::default());
+
+        let mut expected = Vec::new();
+        let mut step = |app: &mut App, delta: f32| {
+            app.advance_frames(1, delta);
+            expected.push(states(app, e));
+        };
+
+        app.simulate_press(KeyCode::Space);
+        step(&mut app, 0.1);
+        app.simulate_axis(GamepadAxisType::LeftStickX, 0.5);
+        step(&mut app, 0.25);
+        app.simulate_press(MouseButton::Left);
+        step(&mut app, 0.05);
+        app.simulate_release(KeyCode::Space);
+        step(&mut app, 0.1);
+        app.simulate_release(MouseButton::Left)
+            .simulate_axis(GamepadAxisType::LeftStickX, 0.);
+        step(&mut app, 0.1);
+
+        let recording = app
+            .world
+            .get_mut::<TestRecorder>(e)
+            .unwrap()
+            .take_recording();
+        (recording, expected)
+    }
+
+    #[test_case(MapFormat::Ron)]
+    #[test_case(MapFormat::Json)]
+    #[test_case(MapFormat::Toml)]
+    #[test_case(MapFormat::Binary)]
+    fn round_trip(format: MapFormat) {
+        let (recording, _) = record();
+        assert_eq!(5, recording.len());
+
+        let bytes = recording.to_bytes(format).unwrap();
+        assert_eq!(
+            recording,
+            TestRecording::from_bytes(format, &bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (recording, _) = record();
+        let dir = std::env::temp_dir().join(format!("bevy_input_{}_recording", std::process::id()));
+        let paths = BindingsPaths {
+            app_name: "game".to_string(),
+            asset_folder: dir.clone(),
+        };
+
+        recording
+            .save(&paths, BindingsLocation::assets("replays/first.ron"))
+            .unwrap();
+        // saved again over the previous recording
+        recording
+            .save(&paths, BindingsLocation::assets("replays/first.ron"))
+            .unwrap();
+        assert_eq!(1, std::fs::read_dir(dir.join("replays")).unwrap().count());
+        assert_eq!(
+            recording,
+            TestRecording::load(&paths, dir.join("replays").join("first.ron")).unwrap()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replay() {
+        let (recording, expected) = record();
+        let deltas: Vec<_> = recording
+            .frames()
+            .iter()
+            .map(|frame| frame.delta_seconds)
+            .collect();
+        assert_eq!(vec![0.1, 0.25, 0.05, 0.1, 0.1], deltas);
+
+        let (mut app, e) = setup_app();
+        app.world.entity_mut(e).insert(InputReplay::new(recording));
+
+        // device input is ignored while replaying
+        app.simulate_press(MouseButton::Left);
+        for (frame, expected) in expected.into_iter().enumerate() {
+            // the replay doesn't depend on the delta of the app
+            app.advance_frames(1, 1.);
+            assert_eq!(expected, states(&app, e), "frame {}", frame);
+        }
+
+        app.advance_frames(1, 1.);
+        let replay = app
+            .world
+            .get::<InputReplay<TestAction, TestAxis>>(e)
+            .unwrap();
+        assert!(replay.is_finished());
+        assert_eq!((None, None, 0.), states(&app, e));
+    }
+}
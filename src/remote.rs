@@ -0,0 +1,504 @@
+use crate::{
+    app_ext::NoAxis, input_time::InputTime, ActionIndices, ActionInput, ActionMapInput,
+    InputSnapshot,
+};
+use bevy::prelude::*;
+use std::collections::{BTreeMap, VecDeque};
+
+const HEADER_SIZE: usize = 5;
+
+// the snapshots of the last frames up to `sequence`, oldest first - every packet repeats
+// the previous frames so a lost packet is covered by the next one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePacket {
+    pub sequence: u32,
+    pub snapshots: Vec<InputSnapshot>,
+}
+
+impl RemotePacket {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count = self.snapshots.len().min(u8::MAX as usize);
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + count * InputSnapshot::SIZE);
+        bytes.extend(self.sequence.to_le_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        bytes.push(count as u8);
+        for snapshot in &self.snapshots[self.snapshots.len() - count..] {
+            bytes.extend(snapshot.to_bytes());
+        }
+
+        bytes
+    }
+
+    // `None` if the bytes aren't a packet
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (header, body) = (bytes.get(..HEADER_SIZE)?, &bytes[HEADER_SIZE..]);
+        let count = usize::from(header[4]);
+        if body.len() != count * InputSnapshot::SIZE {
+            return None;
+        }
+
+        Some(Self {
+            sequence: u32::from_le_bytes([header[0], header[1], header[2], header[3]]),
+            snapshots: body
+                .chunks_exact(InputSnapshot::SIZE)
+                .map(|chunk| {
+                    let mut snapshot = [0; InputSnapshot::SIZE];
+                    snapshot.copy_from_slice(chunk);
+                    InputSnapshot::from_bytes(snapshot)
+                })
+                .collect(),
+        })
+    }
+}
+
+// numbers the local snapshots and packs them with the previous ones
+#[derive(Debug, Clone)]
+pub struct RemoteInputSender {
+    next_sequence: u32,
+    history: VecDeque<InputSnapshot>,
+    redundancy: usize,
+}
+
+impl Default for RemoteInputSender {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl RemoteInputSender {
+    // every packet carries the current frame and up to `redundancy` previous ones
+    pub fn new(redundancy: usize) -> Self {
+        Self {
+            next_sequence: 0,
+            history: VecDeque::new(),
+            redundancy: redundancy.min(u8::MAX as usize - 1),
+        }
+    }
+
+    pub fn packet(&mut self, snapshot: InputSnapshot) -> RemotePacket {
+        self.history.push_back(snapshot);
+        while self.history.len() > self.redundancy + 1 {
+            self.history.pop_front();
+        }
+
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        RemotePacket {
+            sequence,
+            snapshots: self.history.iter().copied().collect(),
+        }
+    }
+}
+
+// drives the `ActionInput` of the same entity from the snapshots of a remote player,
+// one frame of the remote player is applied per local frame - the entity still needs
+// an `ActionMap` to get an `ActionInput`, it can be left empty
+#[derive(Component)]
+pub struct RemoteInput<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput = NoAxis> {
+    indices: ActionIndices<TKeyAction, TAxisAction>,
+    // frames further ahead are skipped to keep the latency bounded
+    max_buffered: usize,
+    // frames further ahead of the next one are rejected as garbage
+    max_ahead: u32,
+    buffer: BTreeMap<u32, InputSnapshot>,
+    next_sequence: u32,
+    last_snapshot: InputSnapshot,
+    lost_frames: u32,
+    repeated_frames: u32,
+    state: ActionInput<TKeyAction, TAxisAction>,
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> RemoteInput<TKeyAction, TAxisAction> {
+    pub fn new(indices: ActionIndices<TKeyAction, TAxisAction>) -> Self {
+        Self {
+            indices,
+            max_buffered: 8,
+            max_ahead: 1024,
+            buffer: BTreeMap::new(),
+            next_sequence: 0,
+            last_snapshot: InputSnapshot::default(),
+            lost_frames: 0,
+            repeated_frames: 0,
+            state: ActionInput::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_buffered(mut self, max_buffered: usize) -> Self {
+        self.max_buffered = max_buffered.max(1);
+        self
+    }
+
+    // frames this far past the next frame or further are ignored
+    #[must_use]
+    pub fn with_max_ahead(mut self, max_ahead: u32) -> Self {
+        // half the sequence space is needed to tell late frames apart
+        self.max_ahead = max_ahead.clamp(1, u32::MAX / 2);
+        self
+    }
+
+    // the sequence number of the next frame to apply
+    pub fn next_sequence(&self) -> u32 {
+        self.next_sequence
+    }
+
+    // frames that never arrived and were skipped
+    pub fn lost_frames(&self) -> u32 {
+        self.lost_frames
+    }
+
+    // local frames without a new remote frame, the last one was applied again
+    pub fn repeated_frames(&self) -> u32 {
+        self.repeated_frames
+    }
+
+    // late and duplicate frames are ignored, as are frames too far ahead to be genuine,
+    // sequence numbers wrap around and are compared with serial number arithmetic (RFC 1982)
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn receive(&mut self, packet: &RemotePacket) {
+        let count = packet.snapshots.len() as u32;
+        for (i, snapshot) in packet.snapshots.iter().enumerate() {
+            let sequence = packet.sequence.wrapping_sub(count - 1 - i as u32);
+            if self.frames_ahead(sequence) < self.max_ahead {
+                self.buffer.insert(sequence, *snapshot);
+            }
+        }
+    }
+
+    // returns false if the bytes aren't a packet
+    pub fn receive_bytes(&mut self, bytes: &[u8]) -> bool {
+        if let Some(packet) = RemotePacket::from_bytes(bytes) {
+            self.receive(&packet);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_snapshot(&mut self) -> InputSnapshot {
+        while self.buffer.len() > self.max_buffered {
+            self.pop_buffered();
+        }
+
+        if let Some((sequence, snapshot)) = self.pop_buffered() {
+            self.lost_frames += self.frames_ahead(sequence);
+            self.next_sequence = sequence.wrapping_add(1);
+            self.last_snapshot = snapshot;
+        } else {
+            self.repeated_frames += 1;
+        }
+
+        self.last_snapshot
+    }
+
+    // frames before the next one are more than `u32::MAX / 2` ahead
+    fn frames_ahead(&self, sequence: u32) -> u32 {
+        sequence.wrapping_sub(self.next_sequence)
+    }
+
+    // the buffered frames can wrap around, so the oldest isn't always the smallest
+    fn pop_buffered(&mut self) -> Option<(u32, InputSnapshot)> {
+        let sequence = *self
+            .buffer
+            .keys()
+            .min_by_key(|sequence| self.frames_ahead(**sequence))?;
+        self.buffer
+            .remove(&sequence)
+            .map(|snapshot| (sequence, snapshot))
+    }
+}
+
+pub(crate) fn apply_remote_input<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(
+        &mut ActionInput<TKeyAction, TAxisAction>,
+        &mut RemoteInput<TKeyAction, TAxisAction>,
+    )>,
+    time: Res<InputTime>,
+) {
+    for (mut input, mut remote) in input_q.iter_mut() {
+        let remote = &mut *remote;
+        let snapshot = remote.next_snapshot();
+        remote
+            .state
+            .from_snapshot(snapshot, &remote.indices, time.delta_seconds());
+
+        input
+            .button_actions
+            .clone_from(&remote.state.button_actions);
+        input
+            .press_timestamps
+            .clone_from(&remote.state.press_timestamps);
+        input.axes.clone_from(&remote.state.axes);
+    }
+}
+
+// an in-process transport that hands sent packets straight to the receiver
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackTransport {
+    queue: std::sync::Arc<std::sync::Mutex<VecDeque<Vec<u8>>>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+#[allow(clippy::missing_panics_doc)]
+impl LoopbackTransport {
+    pub fn send(&self, packet: Vec<u8>) {
+        self.queue.lock().unwrap().push_back(packet);
+    }
+
+    pub fn receive(&self) -> Option<Vec<u8>> {
+        self.queue.lock().unwrap().pop_front()
+    }
+
+    // drops the packets in flight, e.g. to simulate loss
+    pub fn drain(&self) -> Vec<Vec<u8>> {
+        self.queue.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoopbackTransport, RemoteInput, RemoteInputSender, RemotePacket};
+    use crate::{
+        test_support::fixtures::{
+            input, spawn_player, test_app, test_map, TestAction, TestAxis, TestInput, TestMap,
+        },
+        ActionIndices, InputSimulationExt, InputSnapshot,
+    };
+    use bevy::prelude::*;
+    use pretty_assertions::assert_eq;
+
+    type TestRemote = RemoteInput<TestAction, TestAxis>;
+
+    fn indices() -> ActionIndices<TestAction, TestAxis> {
+        ActionIndices::new(
+            [TestAction::Jump, TestAction::Shoot],
+            [TestAxis::Horizontal],
+        )
+    }
+
+    fn snapshot(buttons: u32) -> InputSnapshot {
+        InputSnapshot {
+            buttons,
+            ..Default::default()
+        }
+    }
+
+    // a client sending its local input and a server applying it to the remote player
+    struct Loopback {
+        client: App,
+        client_player: Entity,
+        server: App,
+        remote_player: Entity,
+        sender: RemoteInputSender,
+        transport: LoopbackTransport,
+    }
+
+    impl Loopback {
+        fn new() -> Self {
+            let map = test_map(
+                [
+                    (TestAction::Jump, KeyCode::Space.into()),
+                    (TestAction::Shoot, KeyCode::LControl.into()),
+                ],
+                [],
+            );
+
+            let mut client = test_app();
+            let client_player = spawn_player(&mut client, map);
+
+            let mut server = test_app();
+            let remote_player = spawn_player(&mut server, TestMap::default());
+            server
+                .world
+                .entity_mut(remote_player)
+                .insert(TestRemote::new(indices()));
+
+            // the first update adds the ActionInput components
+            client.advance_frames(1, 0.1);
+            server.advance_frames(1, 0.1);
+
+            Self {
+                client,
+                client_player,
+                server,
+                remote_player,
+                sender: RemoteInputSender::new(2),
+                transport: LoopbackTransport::default(),
+            }
+        }
+
+        fn client_frame(&mut self) {
+            self.client.advance_frames(1, 0.1);
+            let snapshot = input(&self.client, self.client_player).to_snapshot(&indices());
+            self.transport.send(self.sender.packet(snapshot).to_bytes());
+        }
+
+        fn server_frame(&mut self) {
+            while let Some(bytes) = self.transport.receive() {
+                assert!(self
+                    .server
+                    .world
+                    .get_mut::<TestRemote>(self.remote_player)
+                    .unwrap()
+                    .receive_bytes(&bytes));
+            }
+            self.server.advance_frames(1, 0.1);
+        }
+
+        fn server_input(&self) -> &TestInput {
+            input(&self.server, self.remote_player)
+        }
+
+        fn remote(&self) -> &TestRemote {
+            self.server
+                .world
+                .get::<TestRemote>(self.remote_player)
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn packet_round_trip() {
+        let packet = RemotePacket {
+            sequence: 7,
+            snapshots: vec![snapshot(1), snapshot(3)],
+        };
+        assert_eq!(
+            Some(packet.clone()),
+            RemotePacket::from_bytes(&packet.to_bytes())
+        );
+        assert_eq!(None, RemotePacket::from_bytes(&packet.to_bytes()[..9]));
+    }
+
+    #[test]
+    fn loopback() {
+        let mut loopback = Loopback::new();
+        loopback.client_frame();
+        loopback.server_frame();
+
+        loopback.client.simulate_press(KeyCode::Space);
+        loopback.client_frame();
+        loopback.server_frame();
+        assert!(loopback.server_input().just_pressed(TestAction::Jump));
+
+        loopback.client_frame();
+        loopback.server_frame();
+        assert!(loopback.server_input().held(TestAction::Jump));
+
+        loopback.client.simulate_release(KeyCode::Space);
+        loopback.client_frame();
+        loopback.server_frame();
+        assert!(loopback.server_input().just_released(TestAction::Jump));
+        assert_eq!(0, loopback.remote().lost_frames());
+    }
+
+    #[test]
+    fn lost_packets_are_recovered() {
+        let mut loopback = Loopback::new();
+        loopback.client_frame();
+        loopback.server_frame();
+
+        // the press is only sent in a dropped packet
+        loopback.client.simulate_press(KeyCode::LControl);
+        loopback.client_frame();
+        loopback.transport.drain();
+        loopback.server_frame();
+        assert_eq!(1, loopback.remote().repeated_frames());
+
+        loopback.client_frame();
+        loopback.server_frame();
+        assert!(loopback.server_input().just_pressed(TestAction::Shoot));
+        loopback.server_frame();
+        assert!(loopback.server_input().held(TestAction::Shoot));
+        assert_eq!(0, loopback.remote().lost_frames());
+    }
+
+    #[test]
+    fn reordered_packets() {
+        let mut loopback = Loopback::new();
+        loopback.client.simulate_press(KeyCode::Space);
+        loopback.client_frame();
+        loopback.client.simulate_release(KeyCode::Space);
+        loopback.client_frame();
+
+        let mut packets = loopback.transport.drain();
+        packets.reverse();
+        for packet in packets {
+            loopback.transport.send(packet);
+        }
+
+        loopback.server_frame();
+        assert!(loopback.server_input().just_pressed(TestAction::Jump));
+        loopback.server_frame();
+        assert!(loopback.server_input().just_released(TestAction::Jump));
+    }
+
+    #[test]
+    fn frames_beyond_redundancy_are_lost() {
+        let mut remote = TestRemote::new(indices());
+        let mut sender = RemoteInputSender::new(1);
+        for buttons in [1, 0, 0, 1, 1] {
+            let packet = sender.packet(snapshot(buttons));
+            // frame 1 is only carried by the dropped packets
+            if !matches!(packet.sequence, 1 | 2) {
+                remote.receive(&packet);
+            }
+        }
+
+        assert_eq!(snapshot(1), remote.next_snapshot());
+        assert_eq!(0, remote.lost_frames());
+        assert_eq!(snapshot(0), remote.next_snapshot());
+        assert_eq!(1, remote.lost_frames());
+        assert_eq!(3, remote.next_sequence());
+    }
+    #[test]
+    fn sequence_wraps_around() {
+        let mut remote = TestRemote::new(indices());
+        let mut sender = RemoteInputSender::new(2);
+        remote.next_sequence = u32::MAX - 1;
+        sender.next_sequence = u32::MAX - 1;
+
+        for buttons in 0..4 {
+            let packet = sender.packet(snapshot(buttons));
+            // only the last packet arrives, it carries the frames from both sides of the wrap
+            if packet.sequence == 1 {
+                remote.receive(&packet);
+            }
+        }
+
+        // the first frame was only carried by the dropped packets
+        assert_eq!(snapshot(1), remote.next_snapshot());
+        assert_eq!(snapshot(2), remote.next_snapshot());
+        assert_eq!(snapshot(3), remote.next_snapshot());
+        assert_eq!(1, remote.lost_frames());
+        assert_eq!(2, remote.next_sequence());
+    }
+
+    #[test]
+    fn garbage_sequences_are_ignored() {
+        let mut remote = TestRemote::new(indices()).with_max_ahead(16);
+        let mut sender = RemoteInputSender::new(0);
+        remote.receive(&sender.packet(snapshot(1)));
+        assert_eq!(snapshot(1), remote.next_snapshot());
+
+        // late, wrapped around late, just beyond the bound and half the sequence space ahead
+        for sequence in [0, u32::MAX, 1 + 16, 0x8000_0001] {
+            remote.receive(&RemotePacket {
+                sequence,
+                snapshots: vec![snapshot(2)],
+            });
+        }
+        remote.receive(&sender.packet(snapshot(3)));
+
+        assert_eq!(snapshot(3), remote.next_snapshot());
+        assert_eq!(0, remote.lost_frames());
+        assert_eq!(2, remote.next_sequence());
+        // nothing else was buffered
+        assert_eq!(snapshot(3), remote.next_snapshot());
+        assert_eq!(1, remote.repeated_frames());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    action_map::{ButtonState, PlayerData},
+    app_ext::NoAxis,
+    input_time::InputTimestamp,
+    ActionInput, ActionMapInput, ActionState,
+};
+
+pub const MAX_SNAPSHOT_ACTIONS: usize = 32;
+pub const MAX_SNAPSHOT_AXES: usize = 4;
+
+const AXIS_STEPS: f32 = i8::MAX as f32;
+
+// the bit and slot every action takes up in an `InputSnapshot`,
+// has to be the same on every peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionIndices<TKeyAction, TAxisAction = NoAxis> {
+    actions: Vec<TKeyAction>,
+    axes: Vec<TAxisAction>,
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput>
+    ActionIndices<TKeyAction, TAxisAction>
+{
+    /// # Panics
+    ///
+    /// Will panic if there are more than `MAX_SNAPSHOT_ACTIONS` actions or `MAX_SNAPSHOT_AXES` axes
+    pub fn new(
+        actions: impl IntoIterator<Item = TKeyAction>,
+        axes: impl IntoIterator<Item = TAxisAction>,
+    ) -> Self {
+        let actions: Vec<_> = actions.into_iter().collect();
+        let axes: Vec<_> = axes.into_iter().collect();
+        assert!(
+            actions.len() <= MAX_SNAPSHOT_ACTIONS,
+            "snapshots fit at most {} actions",
+            MAX_SNAPSHOT_ACTIONS
+        );
+        assert!(
+            axes.len() <= MAX_SNAPSHOT_AXES,
+            "snapshots fit at most {} axes",
+            MAX_SNAPSHOT_AXES
+        );
+
+        Self { actions, axes }
+    }
+
+    pub fn action_index(&self, action: TKeyAction) -> Option<usize> {
+        self.actions.iter().position(|a| *a == action)
+    }
+
+    pub fn axis_index(&self, axis: TAxisAction) -> Option<usize> {
+        self.axes.iter().position(|a| *a == axis)
+    }
+
+    pub fn action(&self, index: usize) -> Option<TKeyAction> {
+        self.actions.get(index).copied()
+    }
+
+    pub fn axis(&self, index: usize) -> Option<TAxisAction> {
+        self.axes.get(index).copied()
+    }
+}
+
+// the input of a player for a single frame, 8 bytes without padding
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputSnapshot {
+    // bit `i` is set while the action at index `i` is down
+    pub buttons: u32,
+    // axis values scaled to -127..=127
+    pub axes: [i8; MAX_SNAPSHOT_AXES],
+}
+
+impl InputSnapshot {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+
+    pub fn is_down(&self, index: usize) -> bool {
+        index < MAX_SNAPSHOT_ACTIONS && self.buttons & (1 << index) != 0
+    }
+
+    pub fn axis(&self, index: usize) -> f32 {
+        self.axes
+            .get(index)
+            .map_or(0., |value| f32::from(*value) / AXIS_STEPS)
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&self.buttons.to_le_bytes());
+        for (byte, axis) in bytes[4..].iter_mut().zip(self.axes) {
+            *byte = axis.to_le_bytes()[0];
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        let mut snapshot = Self {
+            buttons: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            ..Default::default()
+        };
+        for (axis, byte) in snapshot.axes.iter_mut().zip(&bytes[4..]) {
+            *axis = i8::from_le_bytes([*byte]);
+        }
+
+        snapshot
+    }
+}
+
+impl<TKeyAction: ActionMapInput, TAxisAction: ActionMapInput> ActionInput<TKeyAction, TAxisAction> {
+    // used actions count as up, so they're released on the peers
+    pub fn to_snapshot(&self, indices: &ActionIndices<TKeyAction, TAxisAction>) -> InputSnapshot {
+        let mut snapshot = InputSnapshot::default();
+
+        for (i, action) in indices.actions.iter().enumerate() {
+            if matches!(
+                self.get_button_action_state(*action),
+                Some(ActionState::Pressed | ActionState::Held(..))
+            ) {
+                snapshot.buttons |= 1 << i;
+            }
+        }
+
+        for (value, axis) in snapshot.axes.iter_mut().zip(&indices.axes) {
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *value = (self.get_axis(axis).clamp(-1., 1.) * AXIS_STEPS).round() as i8;
+            }
+        }
+
+        snapshot
+    }
+
+    // advances the action states to the snapshot, so the transitions only depend on
+    // the previous snapshots and the delta, not on when the snapshot arrived
+    pub fn from_snapshot(
+        &mut self,
+        snapshot: InputSnapshot,
+        indices: &ActionIndices<TKeyAction, TAxisAction>,
+        delta_seconds: f32,
+    ) {
+        // snapshots carry no timing, transitions are stamped with the sum of the deltas
+        self.snapshot_seconds += f64::from(delta_seconds);
+        let timestamp = InputTimestamp::new(self.snapshot_seconds, 0);
+
+        for (i, action) in indices.actions.iter().enumerate() {
+            // a pressed action that's still down is held
+            let state = if snapshot.is_down(i) {
+                Some(ButtonState::Pressed)
+            } else {
+                None
+            };
+            self.update_action_state(PlayerData::new(*action), state, delta_seconds, timestamp);
+        }
+
+        for (i, axis) in indices.axes.iter().enumerate() {
+            self.axes.insert(PlayerData::new(*axis), snapshot.axis(i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActionIndices, InputSnapshot};
+    use crate::{
+        test_support::fixtures::{TestAction, TestAxis, TestInput},
+        ActionState,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn indices() -> ActionIndices<TestAction, TestAxis> {
+        ActionIndices::new(
+            [TestAction::Jump, TestAction::Shoot],
+            [TestAxis::Horizontal, TestAxis::Vertical],
+        )
+    }
+
+    fn snapshot(buttons: u32, horizontal: i8) -> InputSnapshot {
+        InputSnapshot {
+            buttons,
+            axes: [horizontal, 0, 0, 0],
+        }
+    }
+
+    fn jump_state(input: &TestInput) -> Option<String> {
+        input
+            .get_button_action_state(TestAction::Jump)
+            .map(|state| format!("{:?}", state))
+    }
+
+    #[test]
+    fn transitions() {
+        let indices = indices();
+        let mut input = TestInput::default();
+
+        let mut states = Vec::new();
+        for buttons in [0b01, 0b01, 0b11, 0b10, 0b10, 0b01] {
+            input.from_snapshot(snapshot(buttons, 0), &indices, 0.5);
+            states.push(jump_state(&input));
+        }
+
+        assert_eq!(
+            vec![
+                Some("Pressed".to_string()),
+                Some("Pressed: 0.50".to_string()),
+                Some("Pressed: 1.00".to_string()),
+                Some("Released: 1.50".to_string()),
+                None,
+                Some("Pressed".to_string()),
+            ],
+            states
+        );
+        assert!(input.just_released(TestAction::Shoot));
+    }
+
+    #[test]
+    fn round_trip() {
+        let indices = indices();
+        let mut local = TestInput::default();
+        let mut remote = TestInput::default();
+
+        for (buttons, horizontal) in [(0b10, 127), (0b11, -64), (0, 0)] {
+            local.from_snapshot(snapshot(buttons, horizontal), &indices, 1. / 60.);
+
+            let bytes = local.to_snapshot(&indices).to_bytes();
+            assert_eq!(InputSnapshot::SIZE, bytes.len());
+            remote.from_snapshot(InputSnapshot::from_bytes(bytes), &indices, 1. / 60.);
+
+            assert_eq!(local.to_snapshot(&indices), remote.to_snapshot(&indices));
+            assert_eq!(jump_state(&local), jump_state(&remote));
+            assert!(
+                (local.get_axis(&TestAxis::Horizontal) - f32::from(horizontal) / 127.).abs()
+                    < f32::EPSILON
+            );
+        }
+    }
+
+    #[test]
+    fn used_actions_are_up() {
+        let indices = indices();
+        let mut input = TestInput::default();
+        input.from_snapshot(snapshot(0b01, 0), &indices, 0.1);
+        input.use_button_action(TestAction::Jump);
+
+        assert_eq!(0, input.to_snapshot(&indices).buttons);
+        assert!(matches!(
+            input.get_button_action_state(TestAction::Jump),
+            Some(ActionState::Used)
+        ));
+    }
+}
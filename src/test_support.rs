@@ -0,0 +1,335 @@
+use crate::{ButtonCode, InputTime, TouchRegion};
+use bevy::{
+    core::CorePlugin,
+    ecs::{event::Events, system::Resource},
+    input::{
+        gamepad::{GamepadEventRaw, GamepadEventType},
+        keyboard::KeyboardInput,
+        mouse::MouseButtonInput,
+        touch::{TouchInput, TouchPhase},
+        ElementState, InputPlugin,
+    },
+    prelude::*,
+    utils::HashMap,
+};
+
+// simulated gamepad input comes from this gamepad
+pub const SIMULATED_GAMEPAD: Gamepad = Gamepad(0);
+
+// an app with just the plugins the input systems need - no window, no gilrs
+pub fn headless_input_app() -> App {
+    let mut app = App::new();
+    app.add_plugin(CorePlugin).add_plugin(InputPlugin);
+    app
+}
+
+// feeds raw device events to the app, so the regular input systems pick them up on the next update
+pub trait InputSimulationExt {
+    /// # Panics
+    ///
+    /// Will panic for touch gestures, which can't be simulated as a single press
+    fn simulate_press(&mut self, button: impl Into<ButtonCode>) -> &mut Self;
+
+    /// # Panics
+    ///
+    /// Will panic for touch gestures, which can't be simulated as a single release
+    fn simulate_release(&mut self, button: impl Into<ButtonCode>) -> &mut Self;
+
+    fn simulate_axis(&mut self, axis: GamepadAxisType, value: f32) -> &mut Self;
+
+    // runs `frames` updates, each lasting `delta_seconds` as far as the input systems are concerned
+    fn advance_frames(&mut self, frames: usize, delta_seconds: f32) -> &mut Self;
+}
+
+impl InputSimulationExt for App {
+    fn simulate_press(&mut self, button: impl Into<ButtonCode>) -> &mut Self {
+        simulate_button(self, button.into(), true);
+        self
+    }
+
+    fn simulate_release(&mut self, button: impl Into<ButtonCode>) -> &mut Self {
+        simulate_button(self, button.into(), false);
+        self
+    }
+
+    fn simulate_axis(&mut self, axis: GamepadAxisType, value: f32) -> &mut Self {
+        send(
+            self,
+            GamepadEventRaw(
+                SIMULATED_GAMEPAD,
+                GamepadEventType::AxisChanged(axis, value),
+            ),
+        );
+        self
+    }
+
+    fn advance_frames(&mut self, frames: usize, delta_seconds: f32) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(InputTime::default)
+            .set_manual(true);
+
+        for _ in 0..frames {
+            self.world
+                .resource_mut::<InputTime>()
+                .advance(delta_seconds);
+            self.update();
+        }
+
+        self
+    }
+}
+
+// touch ids of the simulated touches that are still down
+#[derive(Default)]
+struct SimulatedTouches {
+    active: HashMap<TouchRegion, u64>,
+    next_id: u64,
+}
+
+fn simulate_button(app: &mut App, button: ButtonCode, pressed: bool) {
+    let state = if pressed {
+        ElementState::Pressed
+    } else {
+        ElementState::Released
+    };
+
+    match button {
+        ButtonCode::Kb(key_code) => send(
+            app,
+            KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key_code),
+                state,
+            },
+        ),
+        ButtonCode::ScanCode(scan_code) => send(
+            app,
+            KeyboardInput {
+                scan_code,
+                key_code: None,
+                state,
+            },
+        ),
+        ButtonCode::Mouse(button) => send(app, MouseButtonInput { button, state }),
+        ButtonCode::Gamepad(button) => send(
+            app,
+            GamepadEventRaw(
+                SIMULATED_GAMEPAD,
+                GamepadEventType::ButtonChanged(button, if pressed { 1. } else { 0. }),
+            ),
+        ),
+        ButtonCode::Touch(region) => {
+            let mut touches = app
+                .world
+                .get_resource_or_insert_with(SimulatedTouches::default);
+            let id = if pressed {
+                let id = touches.next_id;
+                touches.next_id += 1;
+                touches.active.insert(region, id);
+                Some(id)
+            } else {
+                touches.active.remove(&region)
+            };
+
+            if let Some(id) = id {
+                send(
+                    app,
+                    TouchInput {
+                        phase: if pressed {
+                            TouchPhase::Started
+                        } else {
+                            TouchPhase::Ended
+                        },
+                        position: Vec2::new(
+                            (region.left + region.right) as f32 / 2.,
+                            (region.top + region.bottom) as f32 / 2.,
+                        ),
+                        force: None,
+                        id,
+                    },
+                );
+            }
+        }
+        ButtonCode::Gesture(gesture) => {
+            panic!(
+                "{:?} can't be simulated as a button, send `TouchInput` events instead",
+                gesture
+            )
+        }
+    }
+}
+
+fn send<T: Resource>(app: &mut App, event: T) {
+    app.world.resource_mut::<Events<T>>().send(event);
+}
+
+// action types, maps and apps shared by the unit tests
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::headless_input_app;
+    use crate::{ActionInput, ActionMap, AppActionInputExt, AxisBinding, ButtonCode};
+    use bevy::prelude::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+    pub(crate) enum TestAction {
+        Jump,
+        Shoot,
+        Crouch,
+        Interact,
+        Pause,
+    }
+
+    #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+    pub(crate) enum TestAxis {
+        Horizontal,
+        Vertical,
+    }
+
+    // a second action type registered next to the test actions
+    #[cfg(feature = "serialize")]
+    #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+    pub(crate) enum MenuAction {
+        Confirm,
+    }
+
+    pub(crate) type TestMap = ActionMap<TestAction, TestAxis>;
+    pub(crate) type TestInput = ActionInput<TestAction, TestAxis>;
+
+    // binds every button on its own, in order
+    pub(crate) fn test_map(
+        buttons: impl IntoIterator<Item = (TestAction, ButtonCode)>,
+        axes: impl IntoIterator<Item = (TestAxis, AxisBinding)>,
+    ) -> TestMap {
+        let mut map = TestMap::default();
+        for (action, button) in buttons {
+            map.bind_button_action(action, button).unwrap();
+        }
+        for (axis, binding) in axes {
+            map.bind_axis(axis, binding);
+        }
+        map
+    }
+
+    // a headless app running the input systems of the test actions
+    pub(crate) fn test_app() -> App {
+        let mut app = headless_input_app();
+        app.add_action_input_systems_with_axis::<TestAction, TestAxis>();
+        app
+    }
+
+    // the player gets its `ActionInput` in the next update
+    pub(crate) fn spawn_player(app: &mut App, map: TestMap) -> Entity {
+        app.world.spawn().insert(map).id()
+    }
+
+    pub(crate) fn input(app: &App, e: Entity) -> &TestInput {
+        app.world.get::<TestInput>(e).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fixtures::{input, spawn_player, test_app, TestAction, TestAxis, TestMap},
+        InputSimulationExt,
+    };
+    use crate::{AxisBinding, ButtonCode, TouchRegion};
+    use bevy::prelude::*;
+
+    fn setup_app() -> (App, Entity) {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, GamepadButtonType::South)
+            .unwrap()
+            .bind_button_combination_action(
+                TestAction::Shoot,
+                [ButtonCode::Kb(KeyCode::LControl), MouseButton::Left.into()],
+            )
+            .unwrap()
+            .bind_button_action(TestAction::Interact, TouchRegion::new(0, 0, 100, 100))
+            .unwrap()
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )
+            .bind_axis(
+                TestAxis::Vertical,
+                AxisBinding::Buttons(KeyCode::S.into(), KeyCode::W.into()),
+            );
+
+        let mut app = test_app();
+        let e = spawn_player(&mut app, map);
+        app.advance_frames(1, 0.1);
+        (app, e)
+    }
+
+    #[test]
+    fn press_hold_release() {
+        let (mut app, e) = setup_app();
+
+        app.simulate_press(KeyCode::Space).advance_frames(1, 0.1);
+        assert!(input(&app, e).just_pressed(TestAction::Jump));
+
+        app.advance_frames(3, 0.1);
+        assert!(input(&app, e).held(TestAction::Jump));
+        let duration = input(&app, e)
+            .get_button_action_state(TestAction::Jump)
+            .unwrap()
+            .duration();
+        assert!((duration - 0.3).abs() < 1e-4);
+
+        app.simulate_release(KeyCode::Space).advance_frames(1, 0.1);
+        assert!(input(&app, e).just_released(TestAction::Jump));
+
+        app.advance_frames(1, 0.1);
+        assert!(input(&app, e)
+            .get_button_action_state(TestAction::Jump)
+            .is_none());
+    }
+
+    #[test]
+    fn devices() {
+        let (mut app, e) = setup_app();
+
+        app.simulate_press(GamepadButtonType::South)
+            .advance_frames(1, 0.1);
+        assert!(input(&app, e).just_pressed(TestAction::Jump));
+        app.simulate_release(GamepadButtonType::South)
+            .advance_frames(1, 0.1);
+        assert!(input(&app, e).just_released(TestAction::Jump));
+
+        app.simulate_press(MouseButton::Left).advance_frames(1, 0.1);
+        assert!(!input(&app, e).just_pressed(TestAction::Shoot));
+        app.simulate_press(KeyCode::LControl).advance_frames(1, 0.1);
+        assert!(input(&app, e).just_pressed(TestAction::Shoot));
+
+        app.simulate_press(TouchRegion::new(0, 0, 100, 100))
+            .advance_frames(1, 0.1);
+        assert!(input(&app, e).just_pressed(TestAction::Interact));
+        app.simulate_release(TouchRegion::new(0, 0, 100, 100))
+            .advance_frames(1, 0.1);
+        assert!(input(&app, e).just_released(TestAction::Interact));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn axes() {
+        let (mut app, e) = setup_app();
+
+        app.simulate_axis(GamepadAxisType::LeftStickX, 0.5)
+            .simulate_press(KeyCode::W)
+            .advance_frames(1, 0.1);
+        assert_eq!(0.5, input(&app, e).get_axis(&TestAxis::Horizontal));
+        assert_eq!(1., input(&app, e).get_axis(&TestAxis::Vertical));
+
+        app.simulate_axis(GamepadAxisType::LeftStickX, 0.)
+            .simulate_release(KeyCode::W)
+            .advance_frames(1, 0.1);
+        assert_eq!(0., input(&app, e).get_axis(&TestAxis::Horizontal));
+        assert_eq!(0., input(&app, e).get_axis(&TestAxis::Vertical));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{
+    action_map::ButtonState, device::ActiveDeviceSettings, ActionInput, ActionMap, ActionMapInput,
+    ButtonCode, InputDevice, InputTime,
+};
+use bevy::{
+    input::touch::{TouchInput, TouchPhase},
+    prelude::*,
+};
+use std::collections::{HashMap, HashSet};
+
+// logical pixels in the same space as `TouchInput::position` - window coordinates with the origin
+// in the top left corner and y pointing down, so `top` is less than `bottom`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TouchRegion {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl TouchRegion {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.x >= self.left as f32
+            && position.x <= self.right as f32
+            && position.y >= self.top as f32
+            && position.y <= self.bottom as f32
+    }
+}
+
+// floating stick - the center is placed wherever a touch starts within the region,
+// its values are y up like the gamepad sticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TouchStick {
+    pub region: TouchRegion,
+    pub radius: u32,
+}
+
+impl TouchStick {
+    pub fn new(region: TouchRegion, radius: u32) -> Self {
+        Self { region, radius }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TouchGesture {
+    Tap,
+    SwipeLeft,
+    SwipeRight,
+    SwipeUp,
+    SwipeDown,
+    PinchIn,
+    PinchOut,
+}
+
+#[derive(Debug, Clone)]
+pub struct TouchGestureSettings {
+    // seconds
+    pub tap_max_duration: f32,
+    pub tap_max_distance: f32,
+    pub swipe_min_distance: f32,
+    // change of the distance between 2 fingers needed to trigger a pinch
+    pub pinch_min_distance: f32,
+}
+
+impl Default for TouchGestureSettings {
+    fn default() -> Self {
+        Self {
+            tap_max_duration: 0.25,
+            tap_max_distance: 20.,
+            swipe_min_distance: 80.,
+            pinch_min_distance: 40.,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct GestureTracker {
+    touches: HashMap<u64, (Vec2, f64)>,
+    multi_touch: bool,
+    pinch_distance: Option<f32>,
+    gestures: HashSet<TouchGesture>,
+}
+
+impl GestureTracker {
+    fn update(
+        &mut self,
+        events: impl Iterator<Item = TouchInput>,
+        touches: &Touches,
+        now: f64,
+        settings: &TouchGestureSettings,
+    ) {
+        self.gestures.clear();
+
+        for ev in events {
+            match ev.phase {
+                TouchPhase::Started => {
+                    self.touches.insert(ev.id, (ev.position, now));
+                }
+                TouchPhase::Moved => {}
+                TouchPhase::Ended => {
+                    if let Some((start_pos, start_time)) = self.touches.remove(&ev.id) {
+                        if !self.multi_touch {
+                            #[allow(clippy::cast_possible_truncation)]
+                            if let Some(gesture) = single_touch_gesture(
+                                ev.position - start_pos,
+                                (now - start_time) as f32,
+                                settings,
+                            ) {
+                                self.gestures.insert(gesture);
+                            }
+                        }
+                    }
+                }
+                TouchPhase::Cancelled => {
+                    self.touches.remove(&ev.id);
+                }
+            }
+        }
+
+        let pressed: Vec<_> = touches.iter().collect();
+        if pressed.len() == 2 {
+            self.multi_touch = true;
+            let distance = pressed[0].position().distance(pressed[1].position());
+            match self.pinch_distance {
+                Some(start) if distance - start >= settings.pinch_min_distance => {
+                    self.gestures.insert(TouchGesture::PinchOut);
+                    self.pinch_distance = Some(distance);
+                }
+                Some(start) if start - distance >= settings.pinch_min_distance => {
+                    self.gestures.insert(TouchGesture::PinchIn);
+                    self.pinch_distance = Some(distance);
+                }
+                Some(_) => {}
+                None => self.pinch_distance = Some(distance),
+            }
+        } else {
+            self.pinch_distance = None;
+
+            if pressed.is_empty() {
+                self.multi_touch = false;
+            } else if pressed.len() > 2 {
+                self.multi_touch = true;
+            }
+        }
+    }
+}
+
+fn single_touch_gesture(
+    delta: Vec2,
+    duration: f32,
+    settings: &TouchGestureSettings,
+) -> Option<TouchGesture> {
+    let distance = delta.length();
+
+    if duration <= settings.tap_max_duration && distance <= settings.tap_max_distance {
+        Some(TouchGesture::Tap)
+    } else if distance >= settings.swipe_min_distance {
+        if delta.x.abs() > delta.y.abs() {
+            if delta.x > 0. {
+                Some(TouchGesture::SwipeRight)
+            } else {
+                Some(TouchGesture::SwipeLeft)
+            }
+        } else if delta.y > 0. {
+            // touch positions are y down
+            Some(TouchGesture::SwipeDown)
+        } else {
+            Some(TouchGesture::SwipeUp)
+        }
+    } else {
+        None
+    }
+}
+
+fn next_button_state(was_down: bool, is_down: bool) -> Option<ButtonState> {
+    match (was_down, is_down) {
+        (false, true) => Some(ButtonState::Pressed),
+        (true, true) => Some(ButtonState::Held),
+        (true, false) => Some(ButtonState::Released),
+        (false, false) => None,
+    }
+}
+
+pub(crate) fn handle_touch_input<
+    TKeyAction: ActionMapInput + 'static,
+    TAxisAction: ActionMapInput + 'static,
+>(
+    mut input_q: Query<(
+        &ActionMap<TKeyAction, TAxisAction>,
+        &mut ActionInput<TKeyAction, TAxisAction>,
+    )>,
+    mut touch_events: EventReader<TouchInput>,
+    touches: Res<Touches>,
+    time: Res<InputTime>,
+    settings: Res<TouchGestureSettings>,
+    device_settings: Res<ActiveDeviceSettings>,
+    mut tracker: Local<GestureTracker>,
+) {
+    tracker.update(
+        touch_events.iter().copied(),
+        &touches,
+        time.seconds_since_startup(),
+        &settings,
+    );
+
+    for (map, mut input) in input_q.iter_mut() {
+        for btn_data in &map.bound_keys {
+            let is_down = match btn_data.value {
+                ButtonCode::Touch(region) => touches.iter().any(|t| region.contains(t.position())),
+                ButtonCode::Gesture(gesture) => tracker.gestures.contains(&gesture),
+                _ => continue,
+            };
+
+            let state = next_button_state(input.button_is_pressed_or_held(btn_data), is_down);
+            if state == Some(ButtonState::Pressed) {
+                input.last_device = Some(InputDevice::Touch);
+            }
+
+            input.button_states.insert(*btn_data, state);
+        }
+
+        for stick in &map.bound_touch_sticks {
+            let anchor = match input.touch_stick_anchors.get(stick) {
+                Some((id, origin)) if touches.get_pressed(*id).is_some() => Some((*id, *origin)),
+                _ => touches
+                    .iter_just_pressed()
+                    .find(|t| stick.region.contains(t.position()))
+                    .map(|t| (t.id(), t.position())),
+            };
+
+            let value = if let Some((id, origin)) = anchor {
+                input.touch_stick_anchors.insert(*stick, (id, origin));
+                touches.get_pressed(id).map_or(Vec2::ZERO, |t| {
+                    let offset = (t.position() - origin) * Vec2::new(1., -1.);
+                    (offset / stick.radius.max(1) as f32).clamp_length_max(1.)
+                })
+            } else {
+                input.touch_stick_anchors.remove(stick);
+                Vec2::ZERO
+            };
+
+            if value.length() > device_settings.axis_noise_threshold {
+                input.last_device = Some(InputDevice::Touch);
+            }
+
+            input.touch_stick_values.insert(*stick, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TouchGesture, TouchRegion, TouchStick};
+    use crate::{
+        test_support::fixtures::{input, spawn_player, test_app, TestAction, TestAxis, TestMap},
+        AxisBinding,
+    };
+    use bevy::{
+        ecs::event::Events,
+        input::touch::{TouchInput, TouchPhase},
+        prelude::*,
+    };
+
+    fn setup_app(map: TestMap) -> (App, Entity) {
+        let mut app = test_app();
+        let e = spawn_player(&mut app, map);
+        app.update();
+        (app, e)
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        app.world
+            .resource_mut::<Events<TouchInput>>()
+            .send(TouchInput {
+                phase,
+                position: Vec2::new(x, y),
+                force: None,
+                id,
+            });
+    }
+
+    #[test]
+    fn virtual_button() {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Shoot, TouchRegion::new(0, 0, 100, 100))
+            .unwrap();
+        let (mut app, e) = setup_app(map);
+
+        touch(&mut app, 0, TouchPhase::Started, 500., 500.);
+        app.update();
+        assert!(!input(&app, e).just_pressed(TestAction::Shoot));
+
+        touch(&mut app, 1, TouchPhase::Started, 50., 50.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Shoot));
+
+        app.update();
+        assert!(input(&app, e).held(TestAction::Shoot));
+
+        touch(&mut app, 1, TouchPhase::Ended, 50., 50.);
+        app.update();
+        assert!(input(&app, e).just_released(TestAction::Shoot));
+    }
+
+    #[test]
+    fn regions_are_in_window_coordinates() {
+        // a strip along the top edge of the window
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Shoot, TouchRegion::new(0, 0, 800, 100))
+            .unwrap();
+        let (mut app, e) = setup_app(map);
+
+        touch(&mut app, 0, TouchPhase::Started, 400., 550.);
+        app.update();
+        assert!(!input(&app, e).just_pressed(TestAction::Shoot));
+
+        touch(&mut app, 1, TouchPhase::Started, 400., 20.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Shoot));
+    }
+
+    #[test]
+    fn floating_stick() {
+        let stick = TouchStick::new(TouchRegion::new(0, 0, 400, 400), 100);
+        let mut map = TestMap::default();
+        map.bind_axis(TestAxis::Horizontal, AxisBinding::TouchStickX(stick))
+            .bind_axis(TestAxis::Vertical, AxisBinding::TouchStickY(stick));
+        let (mut app, e) = setup_app(map);
+
+        touch(&mut app, 0, TouchPhase::Started, 200., 200.);
+        app.update();
+        assert_eq!(
+            Vec2::ZERO,
+            input(&app, e).get_xy_axes_raw(&TestAxis::Horizontal, &TestAxis::Vertical)
+        );
+
+        // up and to the right
+        touch(&mut app, 0, TouchPhase::Moved, 250., 150.);
+        app.update();
+        assert_eq!(
+            Vec2::new(0.5, 0.5),
+            input(&app, e).get_xy_axes_raw(&TestAxis::Horizontal, &TestAxis::Vertical)
+        );
+
+        touch(&mut app, 0, TouchPhase::Ended, 250., 150.);
+        app.update();
+        assert_eq!(
+            Vec2::ZERO,
+            input(&app, e).get_xy_axes_raw(&TestAxis::Horizontal, &TestAxis::Vertical)
+        );
+    }
+
+    #[test]
+    fn gestures() {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Shoot, TouchGesture::Tap)
+            .unwrap()
+            .bind_button_action(TestAction::Crouch, TouchGesture::SwipeLeft)
+            .unwrap()
+            .bind_button_action(TestAction::Interact, TouchGesture::PinchOut)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, TouchGesture::SwipeUp)
+            .unwrap();
+        let (mut app, e) = setup_app(map);
+
+        touch(&mut app, 0, TouchPhase::Started, 300., 300.);
+        touch(&mut app, 0, TouchPhase::Ended, 302., 301.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Shoot));
+        app.update();
+        assert!(input(&app, e).just_released(TestAction::Shoot));
+
+        touch(&mut app, 1, TouchPhase::Started, 300., 300.);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Ended, 100., 290.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Crouch));
+
+        // towards the top of the window
+        touch(&mut app, 4, TouchPhase::Started, 300., 300.);
+        app.update();
+        touch(&mut app, 4, TouchPhase::Ended, 310., 100.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Jump));
+
+        touch(&mut app, 2, TouchPhase::Started, 300., 300.);
+        touch(&mut app, 3, TouchPhase::Started, 350., 300.);
+        app.update();
+        touch(&mut app, 3, TouchPhase::Moved, 450., 300.);
+        app.update();
+        assert!(input(&app, e).just_pressed(TestAction::Interact));
+        assert!(!input(&app, e).just_pressed(TestAction::Shoot));
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     ActionMap, ActionMapInput, ButtonCode,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BindingError {
     Conflict(PlayerData<HashSet<ButtonCode>>),
 }
@@ -0,0 +1,1021 @@
+use crate::{
+    action_map::{PlayerData, DEADZONE_PRECISION},
+    binding_syntax::{
+        format_axis, format_binding, parse_axis, parse_binding, parse_variant, variant_name,
+        TokenError,
+    },
+    ActionMap, AxisBinding, ButtonCode, MapIoError, SerializableActionInput,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{cmp::Ordering, collections::HashMap, fmt, hash::Hash, marker::PhantomData};
+
+// a single step of a `Migration`, actions are referred to by their serialized name
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationStep {
+    RenameAction { from: String, to: String },
+    DropAction(String),
+    RemapButton { from: ButtonCode, to: ButtonCode },
+}
+
+// upgrades bindings files by one version
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Migration {
+    steps: Vec<MigrationStep>,
+}
+
+impl Migration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn rename_action(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.steps.push(MigrationStep::RenameAction {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn drop_action(mut self, action: impl Into<String>) -> Self {
+        self.steps.push(MigrationStep::DropAction(action.into()));
+        self
+    }
+
+    #[must_use]
+    pub fn remap_button(mut self, from: impl Into<ButtonCode>, to: impl Into<ButtonCode>) -> Self {
+        self.steps.push(MigrationStep::RemapButton {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    pub fn steps(&self) -> &[MigrationStep] {
+        &self.steps
+    }
+
+    fn apply(&self, file: &mut BindingsFile) {
+        for step in &self.steps {
+            for (keys, axes) in file.layers_mut() {
+                match step {
+                    MigrationStep::RenameAction { from, to } => {
+                        rename(keys, from, to);
+                        rename(axes, from, to);
+                    }
+                    MigrationStep::DropAction(dropped) => {
+                        keys.retain(|(action, _)| action != dropped);
+                        axes.retain(|(action, _)| action != dropped);
+                    }
+                    MigrationStep::RemapButton { from, to } => {
+                        for text in keys.iter_mut().flat_map(|(_, entry)| entry.bindings_mut()) {
+                            remap_binding(text, *from, *to);
+                        }
+                        for entry in axes.iter_mut().flat_map(|(_, entry)| entry.bindings_mut()) {
+                            remap_axis(&mut entry.axis, *from, *to);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn rename<V>(actions: &mut [(String, V)], from: &str, to: &str) {
+    for (action, _) in actions.iter_mut().filter(|(action, _)| action == from) {
+        *action = to.to_string();
+    }
+}
+
+// unparsable bindings are left as is and reported when the file is applied
+fn remap_binding(text: &mut String, from: ButtonCode, to: ButtonCode) {
+    if let Ok(mut binding) = parse_binding(text) {
+        if binding.remove(&from) {
+            binding.insert(to);
+            *text = format_binding(&binding);
+        }
+    }
+}
+
+fn remap_axis(text: &mut String, from: ButtonCode, to: ButtonCode) {
+    if let Ok(AxisBinding::Buttons(mut neg, mut pos)) = parse_axis(text) {
+        if neg == from || pos == from {
+            for button in [&mut neg, &mut pos] {
+                if *button == from {
+                    *button = to;
+                }
+            }
+            *text = format_axis(&AxisBinding::Buttons(neg, pos));
+        }
+    }
+}
+
+// version 1 replaced the `key_actions` and `axis_actions` lists with overrides keyed by action name
+const FORMAT_VERSION: u32 = 1;
+
+// the chain of migrations applied to loaded bindings files, files are upgraded to the version 1
+// format first, then a file of version `n` is upgraded by every migration from index `n - 1` onwards
+#[derive(Debug, Default, Clone)]
+pub struct BindingsMigrations {
+    migrations: Vec<Migration>,
+}
+
+impl BindingsMigrations {
+    // version written to saved files
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn version(&self) -> u32 {
+        FORMAT_VERSION + self.migrations.len() as u32
+    }
+
+    pub fn add(&mut self, migration: Migration) -> &mut Self {
+        self.migrations.push(migration);
+        self
+    }
+
+    pub(crate) fn migrate(&self, file: &mut BindingsFile) -> Result<(), MapIoError> {
+        // the version of version 0 files only counted the registered migrations
+        if let Some(legacy) = file.legacy.take() {
+            legacy.upgrade(file);
+            file.version += FORMAT_VERSION;
+        }
+        file.version = file.version.max(FORMAT_VERSION);
+
+        if file.version > self.version() {
+            return Err(MapIoError::UnsupportedVersion {
+                version: file.version,
+                latest: self.version(),
+            });
+        }
+
+        for migration in &self.migrations[(file.version - FORMAT_VERSION) as usize..] {
+            migration.apply(file);
+        }
+
+        file.version = self.version();
+        Ok(())
+    }
+}
+
+// the bindings of an action relative to its defaults, written as a plain list when the action
+// was fully rebound and as `{ add: [..], remove: [..] }` otherwise
+#[derive(Debug, Clone, PartialEq)]
+enum Override<B> {
+    Replace(Vec<B>),
+    Amend { add: Vec<B>, remove: Vec<B> },
+}
+
+// human readable form, a single binding can be written without the list
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TextOverride<B> {
+    Replace(Vec<B>),
+    Single(B),
+    Amend(TextAmend<B>),
+}
+
+// misspelled keys would otherwise make an empty amendment
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextAmend<B> {
+    #[serde(default = "Vec::new")]
+    add: Vec<B>,
+    #[serde(default = "Vec::new")]
+    remove: Vec<B>,
+}
+
+// binary formats can't tell the variants apart without a tag
+#[derive(Serialize, Deserialize)]
+enum TaggedOverride<B> {
+    Replace(Vec<B>),
+    Amend { add: Vec<B>, remove: Vec<B> },
+}
+
+impl<B: Clone + PartialEq> Override<B> {
+    // `None` when the action is bound to its defaults
+    fn diff(defaults: &[B], current: &[B]) -> Option<Self> {
+        let add: Vec<B> = current
+            .iter()
+            .filter(|b| !defaults.contains(b))
+            .cloned()
+            .collect();
+        let remove: Vec<B> = defaults
+            .iter()
+            .filter(|b| !current.contains(b))
+            .cloned()
+            .collect();
+
+        if add.is_empty() && remove.is_empty() {
+            None
+        } else if remove.len() == defaults.len() {
+            // fully rebound actions don't pick up defaults added later
+            Some(Self::Replace(current.to_vec()))
+        } else {
+            Some(Self::Amend { add, remove })
+        }
+    }
+
+    fn apply(self, bindings: &mut Vec<B>) {
+        let add = match self {
+            Self::Replace(add) => {
+                bindings.clear();
+                add
+            }
+            Self::Amend { add, remove } => {
+                bindings.retain(|b| !remove.contains(b));
+                add
+            }
+        };
+
+        for b in add {
+            if !bindings.contains(&b) {
+                bindings.push(b);
+            }
+        }
+    }
+}
+
+impl<B> Override<B> {
+    fn bindings_mut(&mut self) -> impl Iterator<Item = &mut B> {
+        let (add, remove) = match self {
+            Self::Replace(add) => (add, None),
+            Self::Amend { add, remove } => (add, Some(remove)),
+        };
+        add.iter_mut().chain(remove.into_iter().flatten())
+    }
+
+    fn sort_by(&mut self, mut compare: impl FnMut(&B, &B) -> Ordering) {
+        match self {
+            Self::Replace(bindings) => bindings.sort_by(&mut compare),
+            Self::Amend { add, remove } => {
+                add.sort_by(&mut compare);
+                remove.sort_by(&mut compare);
+            }
+        }
+    }
+
+    fn try_map<T, E>(self, mut f: impl FnMut(B) -> Result<T, E>) -> Result<Override<T>, E> {
+        let mut map = |bindings: Vec<B>| bindings.into_iter().map(&mut f).collect::<Result<_, _>>();
+        Ok(match self {
+            Self::Replace(bindings) => Override::Replace(map(bindings)?),
+            Self::Amend { add, remove } => Override::Amend {
+                add: map(add)?,
+                remove: map(remove)?,
+            },
+        })
+    }
+
+    fn map<T>(self, mut f: impl FnMut(B) -> T) -> Override<T> {
+        match self.try_map(|b| Ok::<_, std::convert::Infallible>(f(b))) {
+            Ok(entry) => entry,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl<B: Serialize + Clone> Serialize for Override<B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.clone(), serializer.is_human_readable()) {
+            (Self::Replace(bindings), true) => {
+                TextOverride::Replace(bindings).serialize(serializer)
+            }
+            (Self::Amend { add, remove }, true) => {
+                TextOverride::Amend(TextAmend { add, remove }).serialize(serializer)
+            }
+            (Self::Replace(bindings), false) => {
+                TaggedOverride::Replace(bindings).serialize(serializer)
+            }
+            (Self::Amend { add, remove }, false) => {
+                TaggedOverride::Amend { add, remove }.serialize(serializer)
+            }
+        }
+    }
+}
+
+impl<'de, B: Deserialize<'de>> Deserialize<'de> for Override<B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(if deserializer.is_human_readable() {
+            match TextOverride::deserialize(deserializer)? {
+                TextOverride::Replace(bindings) => Self::Replace(bindings),
+                TextOverride::Single(binding) => Self::Replace(vec![binding]),
+                TextOverride::Amend(TextAmend { add, remove }) => Self::Amend { add, remove },
+            }
+        } else {
+            match TaggedOverride::deserialize(deserializer)? {
+                TaggedOverride::Replace(bindings) => Self::Replace(bindings),
+                TaggedOverride::Amend { add, remove } => Self::Amend { add, remove },
+            }
+        })
+    }
+}
+
+// an axis binding, written as a plain string unless it has a deadzone
+#[derive(Debug, Clone, PartialEq)]
+struct AxisEntry {
+    axis: String,
+    deadzone: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TextAxisEntry {
+    Plain(String),
+    WithDeadzone { axis: String, deadzone: f32 },
+}
+
+impl AxisEntry {
+    fn from_binding((binding, deadzone): (AxisBinding, u32)) -> Self {
+        Self {
+            axis: format_axis(&binding),
+            deadzone: deadzone as f32 / DEADZONE_PRECISION,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn to_binding(&self) -> Result<(AxisBinding, u32), TokenError> {
+        Ok((
+            parse_axis(&self.axis)?,
+            (self.deadzone * DEADZONE_PRECISION) as u32,
+        ))
+    }
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.axis
+            .cmp(&other.axis)
+            .then(self.deadzone.total_cmp(&other.deadzone))
+    }
+}
+
+impl Serialize for AxisEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return (&self.axis, self.deadzone).serialize(serializer);
+        }
+
+        #[allow(clippy::float_cmp)]
+        if self.deadzone == 0. {
+            TextAxisEntry::Plain(self.axis.clone()).serialize(serializer)
+        } else {
+            TextAxisEntry::WithDeadzone {
+                axis: self.axis.clone(),
+                deadzone: self.deadzone,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AxisEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let (axis, deadzone) = <(String, f32)>::deserialize(deserializer)?;
+            return Ok(Self { axis, deadzone });
+        }
+
+        Ok(match TextAxisEntry::deserialize(deserializer)? {
+            TextAxisEntry::Plain(axis) => Self { axis, deadzone: 0. },
+            TextAxisEntry::WithDeadzone { axis, deadzone } => Self { axis, deadzone },
+        })
+    }
+}
+
+// action names and their overrides, written as a map but kept in file order
+type KeyOverrides = Vec<(String, Override<String>)>;
+type AxisOverrides = Vec<(String, Override<AxisEntry>)>;
+
+mod ordered_map {
+    use super::{fmt, PhantomData};
+    use serde::{
+        de::{MapAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub(super) fn serialize<V: Serialize, S: Serializer>(
+        entries: &[(String, V)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(entries.iter().map(|(key, value)| (key, value)))
+    }
+
+    pub(super) fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, V)>, D::Error> {
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+
+    struct EntriesVisitor<V>(PhantomData<V>);
+
+    impl<'de, V: Deserialize<'de>> Visitor<'de> for EntriesVisitor<V> {
+        type Value = Vec<(String, V)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of action names to bindings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+}
+
+// overrides of the actions bound to a single player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PlayerOverrides {
+    player: usize,
+    #[serde(default, with = "ordered_map")]
+    keys: KeyOverrides,
+    #[serde(default, with = "ordered_map")]
+    axes: AxisOverrides,
+}
+
+// the versioned envelope actually written to bindings files,
+// holds the user overrides of the default bindings keyed by action name,
+// saved files list actions in their `Ord` order, which is declaration order for derived enums,
+// key bindings keep the order they were bound in and axis bindings are sorted by their text
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+pub(crate) struct BindingsFile {
+    version: u32,
+    #[serde(default, with = "ordered_map")]
+    keys: KeyOverrides,
+    #[serde(default, with = "ordered_map")]
+    axes: AxisOverrides,
+    #[serde(default)]
+    players: Vec<PlayerOverrides>,
+    #[serde(skip)]
+    legacy: Option<LegacyBindings>,
+}
+
+impl Serialize for BindingsFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+// version 0 files are only recognized in text formats
+impl<'de> Deserialize<'de> for BindingsFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return Self::deserialize(deserializer);
+        }
+
+        let file = TextBindingsFile::deserialize(deserializer)?;
+        let legacy = match (file.key_actions, file.axis_actions) {
+            (None, None) => None,
+            (key_actions, axis_actions) => Some(LegacyBindings {
+                key_actions: key_actions.unwrap_or_default(),
+                axis_actions: axis_actions.unwrap_or_default(),
+            }),
+        };
+        Ok(Self {
+            version: file.version,
+            keys: file.keys,
+            axes: file.axes,
+            players: file.players,
+            legacy,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct TextBindingsFile {
+    version: u32,
+    #[serde(default, with = "ordered_map")]
+    keys: KeyOverrides,
+    #[serde(default, with = "ordered_map")]
+    axes: AxisOverrides,
+    #[serde(default)]
+    players: Vec<PlayerOverrides>,
+    #[serde(default, deserialize_with = "present")]
+    key_actions: Option<Vec<LegacyKeyEntry>>,
+    #[serde(default, deserialize_with = "present")]
+    axis_actions: Option<Vec<LegacyAxisEntry>>,
+}
+
+// the legacy lists were written without `Some`
+fn present<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+// version 0 files listed every binding of the actions they contained
+#[derive(Debug, Clone, Deserialize)]
+struct LegacyKeyEntry {
+    action: String,
+    player: Option<usize>,
+    bindings: Vec<Vec<ButtonCode>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LegacyAxisEntry {
+    action: String,
+    player: Option<usize>,
+    bindings: Vec<(AxisBinding, u32)>,
+}
+
+#[derive(Debug, Clone)]
+struct LegacyBindings {
+    key_actions: Vec<LegacyKeyEntry>,
+    axis_actions: Vec<LegacyAxisEntry>,
+}
+
+impl LegacyBindings {
+    // the listed actions replace their defaults
+    fn upgrade(self, file: &mut BindingsFile) {
+        for entry in self.key_actions {
+            let bindings = entry
+                .bindings
+                .into_iter()
+                .map(|binding| format_binding(&binding.into_iter().collect()))
+                .collect();
+            let (keys, _) = file.layer_mut(entry.player);
+            keys.push((entry.action, Override::Replace(bindings)));
+        }
+
+        for entry in self.axis_actions {
+            let mut bindings: Vec<_> = entry
+                .bindings
+                .into_iter()
+                .map(AxisEntry::from_binding)
+                .collect();
+            bindings.sort_by(AxisEntry::cmp);
+            let (_, axes) = file.layer_mut(entry.player);
+            axes.push((entry.action, Override::Replace(bindings)));
+        }
+
+        file.players.sort_by_key(|layer| layer.player);
+    }
+}
+
+impl BindingsFile {
+    pub(crate) fn from_map<
+        TKeyAction: SerializableActionInput,
+        TAxisAction: SerializableActionInput,
+    >(
+        map: &ActionMap<TKeyAction, TAxisAction>,
+        defaults: &ActionMap<TKeyAction, TAxisAction>,
+        version: u32,
+    ) -> Result<Self, MapIoError> {
+        let mut file = Self {
+            version,
+            ..Default::default()
+        };
+
+        let mut key_actions = Vec::new();
+        for action in union_keys(map.get_key_bindings(), defaults.get_key_bindings()) {
+            let default_bindings = binding_list(defaults.get_key_bindings().get(action));
+            let bindings = binding_list(map.get_key_bindings().get(action));
+            if let Some(entry) = Override::diff(&default_bindings, &bindings) {
+                key_actions.push((action, entry.map(|binding| format_binding(&binding))));
+            }
+        }
+
+        key_actions.sort_by_key(|(action, _)| action.value);
+        for (action, entry) in key_actions {
+            let (keys, _) = file.layer_mut(action.id);
+            keys.push((action_name(&action.value)?, entry));
+        }
+
+        let mut axis_actions = Vec::new();
+        for action in union_keys(map.get_axis_bindings(), defaults.get_axis_bindings()) {
+            let default_bindings = binding_list(defaults.get_axis_bindings().get(action));
+            let bindings = binding_list(map.get_axis_bindings().get(action));
+            if let Some(entry) = Override::diff(&default_bindings, &bindings) {
+                let mut entry = entry.map(AxisEntry::from_binding);
+                entry.sort_by(AxisEntry::cmp);
+                axis_actions.push((action, entry));
+            }
+        }
+
+        axis_actions.sort_by_key(|(action, _)| action.value);
+        for (action, entry) in axis_actions {
+            let (_, axes) = file.layer_mut(action.id);
+            axes.push((action_name(&action.value)?, entry));
+        }
+
+        file.players.sort_by_key(|layer| layer.player);
+        Ok(file)
+    }
+
+    // migrates the file and applies its overrides on top of the defaults,
+    // unknown actions are skipped when `lenient`
+    pub(crate) fn into_map<
+        TKeyAction: SerializableActionInput,
+        TAxisAction: SerializableActionInput,
+    >(
+        mut self,
+        defaults: &ActionMap<TKeyAction, TAxisAction>,
+        migrations: &BindingsMigrations,
+        lenient: bool,
+    ) -> Result<ActionMap<TKeyAction, TAxisAction>, MapIoError> {
+        migrations.migrate(&mut self)?;
+
+        let mut key_bindings = defaults.get_key_bindings().clone();
+        let mut axis_bindings = defaults.get_axis_bindings().clone();
+        let players = self
+            .players
+            .into_iter()
+            .map(|layer| (Some(layer.player), layer.keys, layer.axes));
+        for (player, keys, axes) in std::iter::once((None, self.keys, self.axes)).chain(players) {
+            for (name, entry) in keys {
+                if let Some(action) = parse_action(&name, lenient)? {
+                    let action = PlayerData {
+                        id: player,
+                        value: action,
+                    };
+                    entry
+                        .try_map(|text| parse_binding(&text))
+                        .map_err(|err| invalid_token(&name, err))?
+                        .apply(key_bindings.entry(action).or_default());
+                }
+            }
+
+            for (name, entry) in axes {
+                if let Some(action) = parse_action(&name, lenient)? {
+                    let action = PlayerData {
+                        id: player,
+                        value: action,
+                    };
+                    let entry = entry
+                        .try_map(|entry| entry.to_binding())
+                        .map_err(|err| invalid_token(&name, err))?;
+                    let bindings = axis_bindings.entry(action).or_default();
+                    let mut list: Vec<_> = bindings.drain().collect();
+                    entry.apply(&mut list);
+                    bindings.extend(list);
+                }
+            }
+        }
+
+        let mut map = ActionMap::default();
+        map.try_set_bindings(key_bindings, axis_bindings)?;
+        Ok(map)
+    }
+
+    fn layer_mut(&mut self, player: Option<usize>) -> (&mut KeyOverrides, &mut AxisOverrides) {
+        match player {
+            None => (&mut self.keys, &mut self.axes),
+            Some(player) => {
+                let index = if let Some(index) =
+                    self.players.iter().position(|layer| layer.player == player)
+                {
+                    index
+                } else {
+                    self.players.push(PlayerOverrides {
+                        player,
+                        keys: Vec::new(),
+                        axes: Vec::new(),
+                    });
+                    self.players.len() - 1
+                };
+                let layer = &mut self.players[index];
+                (&mut layer.keys, &mut layer.axes)
+            }
+        }
+    }
+
+    fn layers_mut(&mut self) -> impl Iterator<Item = (&mut KeyOverrides, &mut AxisOverrides)> {
+        let players = self
+            .players
+            .iter_mut()
+            .map(|layer| (&mut layer.keys, &mut layer.axes));
+        std::iter::once((&mut self.keys, &mut self.axes)).chain(players)
+    }
+}
+
+fn union_keys<'a, K: Eq + Hash, V>(a: &'a HashMap<K, V>, b: &'a HashMap<K, V>) -> Vec<&'a K> {
+    a.keys()
+        .chain(b.keys().filter(|key| !a.contains_key(key)))
+        .collect()
+}
+
+fn binding_list<'a, B: Clone + 'a>(bindings: Option<impl IntoIterator<Item = &'a B>>) -> Vec<B> {
+    bindings.map_or_else(Vec::new, |bindings| bindings.into_iter().cloned().collect())
+}
+
+fn invalid_token(action: &str, err: TokenError) -> MapIoError {
+    MapIoError::InvalidToken {
+        action: action.to_string(),
+        token: err.token,
+        reason: err.reason,
+    }
+}
+
+// unit variants are stored by their name, anything else as JSON
+fn action_name<T: Serialize>(action: &T) -> Result<String, MapIoError> {
+    match variant_name(action) {
+        Some(name) => Ok(name),
+        None => serde_json::to_string(action).map_err(|err| MapIoError::Serialize(err.to_string())),
+    }
+}
+
+fn parse_action<T: SerializableActionInput>(
+    name: &str,
+    lenient: bool,
+) -> Result<Option<T>, MapIoError> {
+    match parse_variant(name).or_else(|| serde_json::from_str(name).ok()) {
+        Some(action) => Ok(Some(action)),
+        None if lenient => Ok(None),
+        None => Err(MapIoError::UnknownAction(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindingsFile, BindingsMigrations, Migration, Override};
+    use crate::{
+        action_map::PlayerData,
+        test_support::fixtures::{TestAction, TestAxis, TestMap},
+        AxisBinding, ButtonCode, MapFormat, MapIoError,
+    };
+    use bevy::prelude::*;
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    // written before `Fire` was renamed to `Shoot`, `Crouch` was removed and `Q` was swapped for `A`
+    const V0_FILE: &str = r#"(
+    version: 0,
+    key_actions: [
+        (action: "Jump", player: None, bindings: [[Kb(Space)]]),
+        (action: "Fire", player: None, bindings: [[Kb(LControl)]]),
+        (action: "Crouch", player: None, bindings: [[Kb(C)]]),
+    ],
+    axis_actions: [
+        (action: "Horizontal", player: None, bindings: [(Buttons(Kb(Q), Kb(D)), 0)]),
+    ],
+)"#;
+
+    // the same bindings in the version 1 format
+    const V1_FILE: &str = r#"(
+    version: 1,
+    keys: {
+        "Jump": ["Space"],
+        "Fire": "Ctrl",
+        "Crouch": ["C"],
+    },
+    axes: {
+        "Horizontal": ["Q/D"],
+    },
+)"#;
+
+    fn parse(text: &str) -> BindingsFile {
+        MapFormat::Ron.deserialize(text.as_bytes()).unwrap()
+    }
+
+    fn migrations() -> BindingsMigrations {
+        let mut migrations = BindingsMigrations::default();
+        migrations
+            .add(Migration::new().rename_action("Fire", "Shoot"))
+            .add(
+                Migration::new()
+                    .drop_action("Crouch")
+                    .remap_button(KeyCode::Q, KeyCode::A),
+            );
+        migrations
+    }
+
+    fn is_bound(map: &TestMap, action: TestAction, key: KeyCode) -> bool {
+        map.get_key_bindings()
+            .get(&action.into())
+            .map_or(false, |bindings| {
+                bindings.iter().any(|b| b.contains(&key.into()))
+            })
+    }
+
+    #[test_case(V0_FILE ; "version 0 lists")]
+    #[test_case(V1_FILE ; "version 1 overrides")]
+    fn migrate(text: &str) {
+        let map: TestMap = parse(text)
+            .into_map(&TestMap::default(), &migrations(), false)
+            .unwrap();
+
+        assert!(is_bound(&map, TestAction::Jump, KeyCode::Space));
+        assert!(is_bound(&map, TestAction::Shoot, KeyCode::LControl));
+        assert!(
+            map.get_axis_bindings()[&TestAxis::Horizontal.into()].contains(&(
+                AxisBinding::Buttons(KeyCode::A.into(), KeyCode::D.into()),
+                0
+            ))
+        );
+    }
+
+    #[test]
+    fn unknown_action() {
+        let mut migrations = BindingsMigrations::default();
+        migrations.add(Migration::new().rename_action("Fire", "Shoot"));
+        // an action that was removed from the game without a migration
+        let text = V0_FILE.replace("Crouch", "Sprint");
+
+        let res =
+            parse(&text).into_map::<TestAction, TestAxis>(&TestMap::default(), &migrations, false);
+        assert!(matches!(res, Err(MapIoError::UnknownAction(action)) if action == "Sprint"));
+
+        let map: TestMap = parse(&text)
+            .into_map(&TestMap::default(), &migrations, true)
+            .unwrap();
+        assert!(is_bound(&map, TestAction::Shoot, KeyCode::LControl));
+        assert_eq!(2, map.get_key_bindings().len());
+    }
+
+    #[test]
+    fn newer_version() {
+        let res = parse(&V1_FILE.replace("version: 1", "version: 4"))
+            .into_map::<TestAction, TestAxis>(&TestMap::default(), &migrations(), false);
+        assert!(matches!(
+            res,
+            Err(MapIoError::UnsupportedVersion {
+                version: 4,
+                latest: 3
+            })
+        ));
+        // version 0 files only counted the migrations
+        let res = parse(&V0_FILE.replace("version: 0", "version: 3"))
+            .into_map::<TestAction, TestAxis>(&TestMap::default(), &migrations(), false);
+        assert!(matches!(
+            res,
+            Err(MapIoError::UnsupportedVersion {
+                version: 4,
+                latest: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn saved_files_are_current() {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Shoot, KeyCode::LControl)
+            .unwrap();
+        assert_eq!(1, BindingsMigrations::default().version());
+        let file =
+            BindingsFile::from_map(&map, &TestMap::default(), migrations().version()).unwrap();
+        assert_eq!(3, file.version);
+
+        let loaded: TestMap = file
+            .into_map(&TestMap::default(), &migrations(), false)
+            .unwrap();
+        assert_eq!(map.get_key_bindings(), loaded.get_key_bindings());
+    }
+
+    fn overridden_defaults() -> TestMap {
+        let mut defaults = TestMap::default();
+        defaults
+            .bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, KeyCode::Up)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::LControl)
+            .unwrap();
+        defaults
+    }
+
+    // jump is amended and shoot replaced
+    fn overriding_map() -> TestMap {
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::Up)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, KeyCode::W)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, KeyCode::F)
+            .unwrap()
+            .bind_axis(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            );
+        map
+    }
+
+    #[test]
+    fn overrides_follow_new_defaults() {
+        let mut defaults = overridden_defaults();
+        let map = overriding_map();
+
+        let file = BindingsFile::from_map(&map, &defaults, 1).unwrap();
+        assert_eq!(
+            Override::Amend {
+                add: vec!["W".to_string()],
+                remove: vec!["Space".to_string()]
+            },
+            file.keys[0].1
+        );
+        assert_eq!(Override::Replace(vec!["F".to_string()]), file.keys[1].1);
+        assert_eq!(1, file.axes.len());
+
+        // a patch adds gamepad defaults
+        defaults
+            .bind_button_action(TestAction::Jump, GamepadButtonType::South)
+            .unwrap()
+            .bind_button_action(TestAction::Shoot, GamepadButtonType::RightTrigger2)
+            .unwrap();
+        let loaded: TestMap = file
+            .into_map(&defaults, &BindingsMigrations::default(), false)
+            .unwrap();
+
+        assert!(!is_bound(&loaded, TestAction::Jump, KeyCode::Space));
+        assert!(is_bound(&loaded, TestAction::Jump, KeyCode::Up));
+        assert!(is_bound(&loaded, TestAction::Jump, KeyCode::W));
+        assert!(loaded.get_key_bindings()[&TestAction::Jump.into()]
+            .iter()
+            .any(|b| b.contains(&GamepadButtonType::South.into())));
+        // shoot was rebound completely so the new default is not added
+        assert_eq!(
+            vec![[ButtonCode::from(KeyCode::F)]
+                .into_iter()
+                .collect::<HashSet<_>>()],
+            loaded.get_key_bindings()[&TestAction::Shoot.into()]
+        );
+        assert_eq!(map.get_axis_bindings(), loaded.get_axis_bindings());
+    }
+
+    #[test_case(MapFormat::Ron)]
+    #[test_case(MapFormat::Json)]
+    #[test_case(MapFormat::Toml)]
+    fn overrides_round_trip(format: MapFormat) {
+        let defaults = overridden_defaults();
+        let map = overriding_map();
+        let file = BindingsFile::from_map(&map, &defaults, 1).unwrap();
+
+        let bytes = format.serialize(&file).unwrap();
+        let loaded = format.deserialize::<BindingsFile>(&bytes).unwrap();
+        // toml writes the tables of amended actions after the plain lists
+        let sorted = |keys: &[(String, Override<String>)]| {
+            let mut keys = keys.to_vec();
+            keys.sort_by(|a, b| a.0.cmp(&b.0));
+            keys
+        };
+        assert_eq!(sorted(&file.keys), sorted(&loaded.keys));
+        assert!(loaded
+            .keys
+            .iter()
+            .any(|(_, bindings)| matches!(bindings, Override::Amend { .. })));
+
+        let loaded: TestMap = loaded
+            .into_map(&defaults, &BindingsMigrations::default(), false)
+            .unwrap();
+        assert_eq!(map.get_key_bindings(), loaded.get_key_bindings());
+    }
+
+    #[test_case(r#"(version: 1, keys: { "Jump": (ad: ["W"]) })"# ; "misspelled")]
+    #[test_case(r#"(version: 1, keys: { "Jump": (add: ["W"], removed: ["Space"]) })"# ; "unknown")]
+    fn unknown_override_keys(text: &str) {
+        assert!(MapFormat::Ron
+            .deserialize::<BindingsFile>(text.as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn human_syntax() {
+        let text = r#"{
+            "version": 1,
+            "keys": { "Jump": ["Space", "Pad:South", "Ctrl+Mouse:Left"] },
+            "axes": { "Horizontal": { "axis": "Pad:LeftStickX", "deadzone": 0.1 } },
+            "players": [{ "player": 1, "keys": { "Shoot": { "add": ["Pad:RightTrigger2"] } } }]
+        }"#;
+        let file: BindingsFile = MapFormat::Json.deserialize(text.as_bytes()).unwrap();
+        let loaded: TestMap = file
+            .into_map(&TestMap::default(), &BindingsMigrations::default(), false)
+            .unwrap();
+
+        let mut map = TestMap::default();
+        map.bind_button_action(TestAction::Jump, KeyCode::Space)
+            .unwrap()
+            .bind_button_action(TestAction::Jump, GamepadButtonType::South)
+            .unwrap()
+            .bind_button_combination_action(
+                TestAction::Jump,
+                [KeyCode::LControl.into(), MouseButton::Left.into()],
+            )
+            .unwrap()
+            .bind_axis_with_deadzone(
+                TestAxis::Horizontal,
+                AxisBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+                0.1,
+            );
+        let mut key_bindings = map.get_key_bindings().clone();
+        key_bindings.insert(
+            PlayerData::new_with_id(TestAction::Shoot, 1),
+            vec![[ButtonCode::from(GamepadButtonType::RightTrigger2)]
+                .into_iter()
+                .collect()],
+        );
+        assert_eq!(&key_bindings, loaded.get_key_bindings());
+        assert_eq!(map.get_axis_bindings(), loaded.get_axis_bindings());
+    }
+
+    #[test]
+    fn invalid_token() {
+        let res = parse(&V1_FILE.replace("\"Ctrl\"", "\"Ctrl+Mouse:Lft\""))
+            .into_map::<TestAction, TestAxis>(&TestMap::default(), &migrations(), false);
+        match res {
+            Err(err @ MapIoError::InvalidToken { .. }) => assert_eq!(
+                "invalid binding `Mouse:Lft` for Shoot: unknown Mouse button",
+                err.to_string()
+            ),
+            res => panic!("unexpected result {:?}", res.map(|_| ())),
+        }
+    }
+}